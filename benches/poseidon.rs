@@ -0,0 +1,85 @@
+//! Hashing an anchored tree with a fresh `Poseidon::new_circom` per hash (the
+//! original code) against the per-arity cached hashers.
+//!
+//! Caching only saves building the round constants and MDS matrix; the
+//! permutation itself dominates. Measured release ratios were 1.1x to 1.4x at
+//! 2^12 leaves and 1.1x at 2^16, so the gain is modest and run-to-run noise is
+//! of the same order.
+//!
+//! cargo bench --bench poseidon -- 12 16
+
+use std::time::{Duration, Instant};
+
+use anchored_merkle_proof::setup::{ValueRange, anchor_setup, generator_setup, range_points, secret_setup};
+use anchored_merkle_proof::{IndexedTree, LEAVES_POSEIDON_DOMAIN, PoseidonCurve, anchored_leaf_hash};
+use ark_bn254::{Fr, G1Affine, G1Projective};
+use ark_ff::{BigInteger, PrimeField};
+use light_poseidon::{Poseidon, PoseidonBytesHasher, PoseidonHasher};
+
+fn uncached_root(points: &[G1Affine], anchor: &G1Affine) -> [u8; 32] {
+    let anchor_fields = G1Projective::point_to_fields(anchor).unwrap();
+
+    let mut layer: Vec<[u8; 32]> = points
+        .iter()
+        .map(|p| {
+            let p_fields = G1Projective::point_to_fields(p).unwrap();
+            let inputs = [vec![Fr::from(LEAVES_POSEIDON_DOMAIN)], anchor_fields.clone(), p_fields].concat();
+            let mut poseidon = Poseidon::<Fr>::new_circom(inputs.len()).unwrap();
+            let hash = poseidon.hash(&inputs).unwrap();
+            let mut bytes = [0u8; 32];
+            let v = hash.into_bigint().to_bytes_be();
+            bytes[32 - v.len()..].copy_from_slice(&v);
+            bytes
+        })
+        .collect();
+
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                let mut poseidon = Poseidon::<Fr>::new_circom(2).unwrap();
+                poseidon.hash_bytes_be(&[&pair[0], &pair[1]]).unwrap()
+            })
+            .collect();
+    }
+    layer[0]
+}
+
+fn cached_root(points: &[G1Affine], anchor: &G1Affine) -> [u8; 32] {
+    let leaves: Vec<[u8; 32]> = points.iter().map(|p| anchored_leaf_hash(anchor, p).unwrap()).collect();
+    IndexedTree::from_leaves(&leaves).root().unwrap()
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let out = f();
+    (out, start.elapsed())
+}
+
+fn main() {
+    let mut bits: Vec<u8> = std::env::args().skip(1).filter_map(|arg| arg.parse().ok()).collect();
+    if bits.is_empty() {
+        bits = vec![12, 16];
+    }
+
+    let (g, _, b) = generator_setup();
+    let secret = secret_setup();
+    let anchor = anchor_setup(&secret, &b);
+    println!("{:>6} {:>14} {:>14} {:>8}", "range", "new_circom", "cached", "ratio");
+
+    for bits in bits {
+        let points = range_points(ValueRange::power_of_two(bits).expect("range fits in u64"), &g, &secret);
+
+        let (uncached, uncached_time) = time(|| uncached_root(&points, &anchor));
+        let (cached, cached_time) = time(|| cached_root(&points, &anchor));
+        assert_eq!(uncached, cached, "roots differ for range {bits}");
+
+        println!(
+            "{:>6} {:>14.2?} {:>14.2?} {:>7.2}x",
+            bits,
+            uncached_time,
+            cached_time,
+            uncached_time.as_secs_f64() / cached_time.as_secs_f64()
+        );
+    }
+}
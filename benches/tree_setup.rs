@@ -0,0 +1,49 @@
+//! Point generation for anchored trees: one scalar multiplication and affine
+//! conversion per leaf (the original builder) against incremental addition
+//! with batch normalization.
+//!
+//! cargo bench --bench tree_setup -- 16 20
+
+use std::time::{Duration, Instant};
+
+use anchored_merkle_proof::setup::{ValueRange, range_points, secret_setup};
+use ark_bn254::{Fr, G1Affine, G1Projective};
+use ark_ec::{AffineRepr, CurveGroup, PrimeGroup};
+
+fn per_leaf_points(range: ValueRange, a: &Fr) -> Vec<G1Affine> {
+    (range.lo..=range.hi)
+        .map(|x| (G1Projective::generator() * (Fr::from(x) * a)).into_affine())
+        .collect()
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let out = f();
+    (out, start.elapsed())
+}
+
+fn main() {
+    let mut bits: Vec<u8> = std::env::args().skip(1).filter_map(|arg| arg.parse().ok()).collect();
+    if bits.is_empty() {
+        bits = vec![16, 20];
+    }
+
+    let a = secret_setup();
+    println!("{:>6} {:>14} {:>14} {:>8}", "range", "per-leaf", "incremental", "speedup");
+
+    for bits in bits {
+        let range = ValueRange::power_of_two(bits).expect("range fits in u64");
+
+        let (naive, naive_time) = time(|| per_leaf_points(range, &a));
+        let (fast, fast_time) = time(|| range_points(range, &G1Affine::generator(), &a));
+        assert_eq!(naive, fast, "builders disagree for range {bits}");
+
+        println!(
+            "{:>6} {:>14.2?} {:>14.2?} {:>7.1}x",
+            bits,
+            naive_time,
+            fast_time,
+            naive_time.as_secs_f64() / fast_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Public audit of a published tree root
+//!
+//! An auditor holds the public parameters, the issuer's anchor U and tree
+//! anchor A_G, and the range or set the issuer claims the tree covers. The
+//! audit checks the DLEQ proof between U and A_G, rebuilds the tree from A_G
+//! and compares it with what was published, without the issuer secret. On a
+//! mismatch the report names the first leaf where the two trees differ, when
+//! the published leaves are available.
+
+use ark_ec::AffineRepr;
+
+use crate::setup::{ValueRange, set_tree, tree_setup_public_with_encoding};
+use crate::tree_anchor::{TreeAnchor, verify_tree_anchor};
+use crate::{AnchoredProofError, IndexedTree, LeafEncoding, PoseidonCurve};
+
+/// The values the issuer claims a tree covers
+pub enum TreeClaim<F> {
+    Range(ValueRange),
+    /// Values in tree order; repeats keep their first position
+    Set(Vec<F>),
+}
+
+/// A tree as published by its issuer
+pub struct PublishedTree<'a> {
+    pub root: [u8; 32],
+    pub leaves_len: usize,
+    /// Published leaves, if any; without them a mismatch cannot be located
+    pub leaves: Option<&'a [[u8; 32]]>,
+    pub leaf_encoding: LeafEncoding,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Match,
+    /// `first_differing_leaf` is the first index where the rebuilt and
+    /// published leaves differ, or where one of them ends
+    Mismatch { first_differing_leaf: Option<usize> },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AuditReport {
+    pub rebuilt_root: [u8; 32],
+    pub rebuilt_leaves_len: usize,
+    pub outcome: AuditOutcome,
+}
+
+/// Checks `tree_anchor` against `anchor`, rebuilds the claimed tree from it and
+/// compares the result with `published`
+///
+/// An invalid tree anchor is an error; a tree that does not match is reported
+/// in `AuditReport::outcome`.
+pub fn audit_tree<C: PoseidonCurve>(
+    generator_g: &C::Affine,
+    generator_b: &C::Affine,
+    anchor: &C::Affine,
+    tree_anchor: &TreeAnchor<C>,
+    claim: &TreeClaim<C::ScalarField>,
+    published: &PublishedTree,
+) -> Result<AuditReport, AnchoredProofError> {
+    verify_tree_anchor(tree_anchor, generator_g, generator_b, anchor)?;
+
+    let a_g = &tree_anchor.point;
+    let tree = match claim {
+        TreeClaim::Range(range) => tree_setup_public_with_encoding(*range, anchor, a_g, published.leaf_encoding)?,
+        TreeClaim::Set(values) => {
+            set_tree(values.iter().copied(), anchor, a_g.into_group(), published.leaf_encoding)?.tree
+        }
+    };
+    let rebuilt_root = tree.root().ok_or(AnchoredProofError::InvalidRange)?;
+
+    let outcome = if rebuilt_root == published.root
+        && tree.leaves_len() == published.leaves_len
+        && published.leaves.is_none_or(|leaves| tree.leaves().as_deref() == Some(leaves))
+    {
+        AuditOutcome::Match
+    } else {
+        let first_differing_leaf = published.leaves.and_then(|leaves| first_difference(&tree, leaves));
+        AuditOutcome::Mismatch { first_differing_leaf }
+    };
+
+    Ok(AuditReport { rebuilt_root, rebuilt_leaves_len: tree.leaves_len(), outcome })
+}
+
+fn first_difference(tree: &IndexedTree, published: &[[u8; 32]]) -> Option<usize> {
+    let rebuilt = tree.leaves()?;
+    rebuilt
+        .iter()
+        .zip(published)
+        .position(|(rebuilt, published)| rebuilt != published)
+        .or_else(|| (rebuilt.len() != published.len()).then(|| rebuilt.len().min(published.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fr, G1Affine};
+    use rand::rngs::OsRng;
+    use zeroize::Zeroizing;
+    use crate::setup::{anchor_setup, generator_setup, secret_setup, tree_setup_range, tree_setup_set};
+    use crate::tree_anchor::tree_anchor_setup;
+
+    struct Issuer {
+        g: G1Affine,
+        b: G1Affine,
+        secret: Zeroizing<Fr>,
+        anchor: G1Affine,
+        tree_anchor: TreeAnchor,
+    }
+
+    impl Issuer {
+        fn new() -> Self {
+            let (g, _, b) = generator_setup();
+            let secret = secret_setup();
+            let anchor = anchor_setup(&secret, &b);
+            let tree_anchor = tree_anchor_setup(&secret, &g, &b, &mut OsRng).unwrap();
+            Issuer { g, b, secret, anchor, tree_anchor }
+        }
+
+        fn audit(&self, claim: &TreeClaim<Fr>, published: &PublishedTree) -> Result<AuditReport, AnchoredProofError> {
+            audit_tree(&self.g, &self.b, &self.anchor, &self.tree_anchor, claim, published)
+        }
+    }
+
+    fn published<'a>(tree: &IndexedTree, leaves: Option<&'a [[u8; 32]]>) -> PublishedTree<'a> {
+        PublishedTree {
+            root: tree.root().unwrap(),
+            leaves_len: tree.leaves_len(),
+            leaves,
+            leaf_encoding: LeafEncoding::Full,
+        }
+    }
+
+    #[test]
+    fn test_audit_matches_issuer_tree() {
+        let issuer = Issuer::new();
+        let range = ValueRange::new(18, 120).unwrap();
+        let tree = tree_setup_range(range, &issuer.g, &issuer.anchor, &issuer.secret).unwrap();
+        let leaves = tree.leaves().unwrap();
+
+        let report = issuer.audit(&TreeClaim::Range(range), &published(&tree, Some(&leaves))).unwrap();
+        assert_eq!(report.outcome, AuditOutcome::Match);
+        assert_eq!(report.rebuilt_root, tree.root().unwrap());
+
+        let values: Vec<Fr> = [40u64, 276, 380].map(Fr::from).to_vec();
+        let set = tree_setup_set(values.clone(), &issuer.g, &issuer.anchor, &issuer.secret).unwrap();
+        let report = issuer.audit(&TreeClaim::Set(values), &published(&set.tree, None)).unwrap();
+        assert_eq!(report.outcome, AuditOutcome::Match);
+    }
+
+    #[test]
+    fn test_audit_names_first_differing_leaf() {
+        let issuer = Issuer::new();
+        let range = ValueRange::power_of_two(4).unwrap();
+        let tree = tree_setup_range(range, &issuer.g, &issuer.anchor, &issuer.secret).unwrap();
+
+        // One leaf swapped for a value outside the claimed range
+        let mut leaves = tree.leaves().unwrap();
+        let outsider = ValueRange::new(99, 99).unwrap();
+        leaves[5] = tree_setup_range(outsider, &issuer.g, &issuer.anchor, &issuer.secret).unwrap().leaves().unwrap()[0];
+        let tampered = IndexedTree::from_leaves(&leaves);
+        let report = issuer.audit(&TreeClaim::Range(range), &published(&tampered, Some(&leaves))).unwrap();
+        assert_eq!(report.outcome, AuditOutcome::Mismatch { first_differing_leaf: Some(5) });
+
+        // Root only: the mismatch is detected but cannot be located
+        let report = issuer.audit(&TreeClaim::Range(range), &published(&tampered, None)).unwrap();
+        assert_eq!(report.outcome, AuditOutcome::Mismatch { first_differing_leaf: None });
+
+        // A larger published tree than claimed differs where the claim ends
+        let larger_range = ValueRange::new(1, 20).unwrap();
+        let larger = tree_setup_range(larger_range, &issuer.g, &issuer.anchor, &issuer.secret).unwrap();
+        let larger_leaves = larger.leaves().unwrap();
+        let report = issuer.audit(&TreeClaim::Range(range), &published(&larger, Some(&larger_leaves))).unwrap();
+        assert_eq!(report.outcome, AuditOutcome::Mismatch { first_differing_leaf: Some(16) });
+    }
+
+    #[test]
+    fn test_audit_rejects_foreign_tree_anchor() {
+        let issuer = Issuer::new();
+        let other = Issuer::new();
+        let range = ValueRange::power_of_two(3).unwrap();
+        let tree = tree_setup_range(range, &other.g, &issuer.anchor, &other.secret).unwrap();
+
+        let forged = Issuer { tree_anchor: other.tree_anchor, ..issuer };
+        assert_eq!(
+            forged.audit(&TreeClaim::Range(range), &published(&tree, None)),
+            Err(AnchoredProofError::InvalidTreeAnchor)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! The anchored proof over Baby Jubjub (`ark_ed_on_bn254`)
+//!
+//! Baby Jubjub's base field is BN254 Fr, the Poseidon field, so point
+//! coordinates are hashed as they are instead of being split into limbs, and a
+//! Circom circuit can redo the point arithmetic natively. Setup, proving and
+//! verification are the generic functions in `setup`, `prove` and `verify`;
+//! this module supplies the generators and type aliases.
+
+use ark_ec::AffineRepr;
+use ark_ed_on_bn254::{EdwardsAffine, EdwardsProjective};
+
+use crate::setup::sample_nums_generator;
+
+pub type ProofInput<'a> = crate::ProofInput<'a, EdwardsProjective>;
+pub type AnchoredProof = crate::AnchoredProof<EdwardsProjective>;
+pub type VerificationContext = crate::verify::VerificationContext<EdwardsProjective>;
+
+/// G is the prime-order subgroup generator; H and B still come from the
+/// non-standard try-and-increment `sample_nums_generator` with cofactor
+/// clearing, not from the RFC 9380 hash-to-curve used for BN254
+pub fn generator_setup() -> (EdwardsAffine, EdwardsAffine, EdwardsAffine) {
+    let first = EdwardsAffine::generator();
+    let second = sample_nums_generator(&[0; 32]);
+    let third = sample_nums_generator(&[1; 32]);
+    (first, second, third)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::CurveGroup;
+    use ark_ed_on_bn254::{Fq, Fr};
+    use ark_serialize::Valid;
+    use crate::setup::ValueRange;
+    use crate::test_utils::Fixture;
+    use crate::verify::verify_anchored_proof;
+    use crate::AnchoredProofError;
+
+    fn fixture(range: ValueRange) -> Fixture<EdwardsProjective> {
+        Fixture::with_generators(generator_setup(), range)
+    }
+
+    #[test]
+    fn test_generators_are_distinct_subgroup_points() {
+        let (g, h, b) = generator_setup();
+        for point in [g, h, b] {
+            assert!(!point.is_zero());
+            assert!(point.check().is_ok());
+        }
+        assert_ne!(g, h);
+        assert_ne!(h, b);
+        assert_ne!(g, b);
+    }
+
+    #[test]
+    fn test_verify_range() {
+        let range = ValueRange::new(18, 120).unwrap();
+        let fixture = fixture(range);
+        let context = fixture.context();
+
+        for age in [18, 19, 64, 119, 120] {
+            let proof = fixture.prove(age);
+            assert_eq!(range.value_at(proof.leaf_index), Some(age));
+            assert_eq!(verify_anchored_proof(&proof, &context), Ok(()));
+        }
+        for age in [17, 121] {
+            assert!(matches!(fixture.try_prove(age), Err(AnchoredProofError::WitnessOutOfRange)));
+        }
+    }
+
+    #[test]
+    fn test_rejects_tampered_proofs() {
+        let fixture = fixture(ValueRange::power_of_two(3).unwrap());
+        let context = fixture.context();
+
+        let mut proof = fixture.prove(5);
+        proof.dleq_proof.response += Fr::from(1u64);
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::DleqFailed));
+
+        let mut proof = fixture.prove(5);
+        proof.schnorr_proof.response += Fr::from(1u64);
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::SchnorrFailed));
+
+        let mut proof = fixture.prove(5);
+        proof.leaf_index = 3;
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_rejects_small_order_component() {
+        let fixture = fixture(ValueRange::power_of_two(3).unwrap());
+        let context = fixture.context();
+        let mut proof = fixture.prove(5);
+
+        // (0, -1) has order 2
+        let torsion = EdwardsAffine::new_unchecked(Fq::from(0u64), -Fq::from(1u64));
+        proof.schnorr_proof.commitment = (proof.schnorr_proof.commitment + torsion).into_affine();
+        assert!(matches!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::InvalidEncoding(_))));
+    }
+}
@@ -1,4 +1,530 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-fn main() {
-    
-}
\ No newline at end of file
+use anchored_merkle_proof::json::{
+    TreeAnchorJson, hash_from_hex, issuer_key_from_json, issuer_key_to_json, key_id_from_hex, leaf_encoding_from_id,
+    default_leaf_encoding_id, params_from_json, params_to_json, point_from_hex, point_to_hex, proof_from_json,
+    proof_to_json, scalar_to_string,
+};
+use anchored_merkle_proof::audit::{AuditOutcome, PublishedTree, TreeClaim, audit_tree};
+use anchored_merkle_proof::issuer::key_id;
+use anchored_merkle_proof::prove::generate_anchored_proof;
+use anchored_merkle_proof::tree_anchor::tree_anchor_setup;
+use anchored_merkle_proof::setup::{ValueRange, secret_setup, tree_setup_range_with_encoding};
+use anchored_merkle_proof::verify::{VerificationContext, verify_anchored_proof};
+use anchored_merkle_proof::{
+    AnchoredProof, IndexedTree, IssuerKeypair, LeafEncoding, ProofInput, PublicParameters, TreeAnchor,
+};
+use ark_bn254::{Fr, G1Affine};
+use rand::rngs::OsRng;
+use zeroize::Zeroizing;
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+/// Anchored Merkle range proofs from the command line
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate the public generators and an issuer key, random or derived
+    /// from a master seed and a label
+    Setup {
+        #[arg(long, default_value = "params.json")]
+        params: PathBuf,
+        /// Issuer secret, encrypted under the passphrase
+        #[arg(long, default_value = "issuer.json")]
+        issuer: PathBuf,
+        /// Public anchor, key id and tree anchor, for verifiers and auditors
+        #[arg(long, default_value = "anchor.json")]
+        anchor: PathBuf,
+        /// Environment variable holding the hex master seed to derive the key from
+        #[arg(long, requires = "label")]
+        master_seed_env: Option<String>,
+        /// Derivation label, e.g. the key epoch
+        #[arg(long, requires = "master_seed_env")]
+        label: Option<String>,
+        /// Environment variable holding the issuer key passphrase
+        #[arg(long, default_value = PASSPHRASE_ENV)]
+        passphrase_env: String,
+        /// Overwrite existing output files, discarding the previous issuer key
+        #[arg(long)]
+        force: bool,
+    },
+    /// Build the anchored tree over 1..=2^range, or over [lo, hi]
+    BuildTree {
+        #[arg(long, required_unless_present_all = ["lo", "hi"], conflicts_with_all = ["lo", "hi"])]
+        range: Option<u8>,
+        #[arg(long, requires = "hi")]
+        lo: Option<u64>,
+        #[arg(long, requires = "lo")]
+        hi: Option<u64>,
+        #[arg(long, default_value = "params.json")]
+        params: PathBuf,
+        #[arg(long, default_value = "issuer.json")]
+        issuer: PathBuf,
+        #[arg(long, default_value = PASSPHRASE_ENV)]
+        passphrase_env: String,
+        #[arg(long, default_value = "tree.json")]
+        out: PathBuf,
+        /// Write only the root and size, not the leaves
+        #[arg(long)]
+        root_only: bool,
+        /// Hash leaves over x-coordinates only, to reproduce a root published
+        /// before y-parity was bound into leaves
+        #[arg(long)]
+        legacy_leaf_encoding: bool,
+    },
+    /// Prove that the witness is one of the tree's values
+    Prove {
+        #[arg(long)]
+        witness: u64,
+        #[arg(long, default_value = "params.json")]
+        params: PathBuf,
+        #[arg(long, default_value = "issuer.json")]
+        issuer: PathBuf,
+        #[arg(long, default_value = PASSPHRASE_ENV)]
+        passphrase_env: String,
+        #[arg(long, default_value = "tree.json")]
+        tree: PathBuf,
+        #[arg(long, default_value = "proof.json")]
+        out: PathBuf,
+        /// Context bound into the proof, e.g. a verifier challenge; verify needs the same value
+        #[arg(long, default_value = "")]
+        context: String,
+    },
+    /// Verify a proof; exits non-zero with the reason on failure
+    Verify {
+        #[arg(long, default_value = "proof.json")]
+        proof: PathBuf,
+        #[arg(long, default_value = "params.json")]
+        params: PathBuf,
+        #[arg(long, default_value = "anchor.json")]
+        anchor: PathBuf,
+        #[arg(long, default_value = "tree.json")]
+        tree: PathBuf,
+        #[arg(long, default_value = "")]
+        context: String,
+        /// Accept a root built with x-only leaves; such trees cannot tell P from -P
+        #[arg(long)]
+        legacy_leaf_encoding: bool,
+    },
+    /// Rebuild a published tree from the anchor bundle and compare it with the
+    /// tree file; exits non-zero on mismatch, naming the first differing leaf
+    Audit {
+        #[arg(long, default_value = "params.json")]
+        params: PathBuf,
+        #[arg(long, default_value = "anchor.json")]
+        anchor: PathBuf,
+        #[arg(long, default_value = "tree.json")]
+        tree: PathBuf,
+        /// Claimed range 1..=2^range; without a claim, the range the tree file records
+        #[arg(long, conflicts_with_all = ["lo", "hi", "values"])]
+        range: Option<u8>,
+        #[arg(long, requires = "hi", conflicts_with = "values")]
+        lo: Option<u64>,
+        #[arg(long, requires = "lo", conflicts_with = "values")]
+        hi: Option<u64>,
+        /// Claimed set of values, comma-separated, in tree order
+        #[arg(long, value_delimiter = ',')]
+        values: Option<Vec<u64>>,
+    },
+    /// Pretty-print a proof
+    Inspect {
+        #[arg(long, default_value = "proof.json")]
+        proof: PathBuf,
+    },
+}
+
+const PASSPHRASE_ENV: &str = "ANCHORED_ISSUER_PASSPHRASE";
+
+#[derive(Serialize, Deserialize)]
+struct AnchorFile {
+    anchor: String,
+    key_id: String,
+    /// A_G and its DLEQ proof, so the tree can be rebuilt without the issuer secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tree_anchor: Option<TreeAnchorJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TreeFile {
+    lo: u64,
+    hi: u64,
+    leaves_len: usize,
+    root: String,
+    /// Legacy tree files must be marked as such; a missing field means full leaves
+    #[serde(default = "default_leaf_encoding_id")]
+    leaf_encoding: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    leaves: Option<Vec<String>>,
+}
+
+type CliResult<T> = Result<T, String>;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Setup { params, issuer, anchor, master_seed_env, label, passphrase_env, force } => {
+            let derivation = master_seed_env.zip(label);
+            setup(&params, &issuer, &anchor, derivation, &passphrase_env, force)
+        }
+        Command::BuildTree { range, lo, hi, params, issuer, passphrase_env, out, root_only, legacy_leaf_encoding } => {
+            let issuer = IssuerSource { params: &params, path: &issuer, passphrase_env: &passphrase_env };
+            let encoding = if legacy_leaf_encoding { LeafEncoding::LegacyXOnly } else { LeafEncoding::Full };
+            value_range(range, lo, hi).and_then(|range| build_tree(range, &issuer, &out, root_only, encoding))
+        }
+        Command::Prove { witness, params, issuer, passphrase_env, tree, out, context } => {
+            let issuer = IssuerSource { params: &params, path: &issuer, passphrase_env: &passphrase_env };
+            prove(witness, &issuer, &tree, &out, &context)
+        }
+        Command::Verify { proof, params, anchor, tree, context, legacy_leaf_encoding } => {
+            let encoding = if legacy_leaf_encoding { LeafEncoding::LegacyXOnly } else { LeafEncoding::Full };
+            verify(&proof, &params, &anchor, &tree, &context, encoding)
+        }
+        Command::Audit { params, anchor, tree, range, lo, hi, values } => {
+            let claim = match values {
+                Some(values) => Ok(Some(TreeClaim::Set(values.into_iter().map(Fr::from).collect()))),
+                None if range.is_none() && lo.is_none() => Ok(None),
+                None => value_range(range, lo, hi).map(|range| Some(TreeClaim::Range(range))),
+            };
+            claim.and_then(|claim| audit(&params, &anchor, &tree, claim))
+        }
+        Command::Inspect { proof } => inspect(&proof),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Where an encrypted issuer key is read from
+struct IssuerSource<'a> {
+    params: &'a Path,
+    path: &'a Path,
+    passphrase_env: &'a str,
+}
+
+fn setup(
+    params_path: &Path,
+    issuer_path: &Path,
+    anchor_path: &Path,
+    derivation: Option<(String, String)>,
+    passphrase_env: &str,
+    force: bool,
+) -> CliResult<()> {
+    // Check every output up front so a refused run leaves no partial setup behind
+    if !force
+        && let Some(path) = [params_path, issuer_path, anchor_path].into_iter().find(|path| path.exists())
+    {
+        return Err(already_exists(path));
+    }
+
+    let params = PublicParameters::default();
+    let passphrase = env_secret(passphrase_env)?;
+
+    let keypair: IssuerKeypair = match derivation {
+        Some((seed_env, label)) => {
+            let seed = Zeroizing::new(
+                hex::decode(env_secret(&seed_env)?.as_bytes()).map_err(|e| format!("{seed_env}: {e}"))?,
+            );
+            IssuerKeypair::derive(&seed, &label, &params.generator_b.point).map_err(|e| e.to_string())?
+        }
+        None => IssuerKeypair::generate(&params.generator_b.point, &mut OsRng),
+    };
+    let envelope = keypair.export(passphrase.as_bytes(), &mut OsRng).map_err(|e| e.to_string())?;
+    let (g, _, b) = params.generators();
+    let tree_anchor: TreeAnchor = tree_anchor_setup(keypair.secret(), &g, &b, &mut OsRng).map_err(|e| e.to_string())?;
+
+    let anchor_file = AnchorFile {
+        anchor: point_to_hex(keypair.anchor()),
+        key_id: hex::encode(keypair.key_id()),
+        tree_anchor: Some(TreeAnchorJson::from(&tree_anchor)),
+    };
+    let anchor_json = serde_json::to_string_pretty(&anchor_file).map_err(|e| e.to_string())?;
+    create_file(params_path, &params_to_json(&params), force)?;
+    create_file(issuer_path, &issuer_key_to_json(&envelope), force)?;
+    create_file(anchor_path, &anchor_json, force)?;
+
+    println!("key id {}", hex::encode(keypair.key_id()));
+    println!("wrote {}, {} and {}", params_path.display(), issuer_path.display(), anchor_path.display());
+    Ok(())
+}
+
+fn value_range(range: Option<u8>, lo: Option<u64>, hi: Option<u64>) -> CliResult<ValueRange> {
+    let range = match (range, lo, hi) {
+        (Some(bits), _, _) => ValueRange::power_of_two(bits),
+        (None, Some(lo), Some(hi)) => ValueRange::new(lo, hi),
+        _ => return Err("either --range or both --lo and --hi are required".to_string()),
+    };
+    range.map_err(|e| e.to_string())
+}
+
+fn build_tree(
+    range: ValueRange,
+    issuer: &IssuerSource,
+    out: &Path,
+    root_only: bool,
+    encoding: LeafEncoding,
+) -> CliResult<()> {
+    let (g, _, _) = read_params(issuer.params)?;
+    let keypair = read_issuer(issuer)?;
+    let tree = tree_setup_range_with_encoding(range, &g, keypair.anchor(), keypair.secret(), encoding)
+        .map_err(|e| e.to_string())?;
+    let root = tree.root().ok_or("tree has no root")?;
+
+    let leaves = if root_only {
+        None
+    } else {
+        let leaves = tree.leaves().ok_or("tree has no leaves")?;
+        Some(leaves.iter().map(hex::encode).collect())
+    };
+
+    write_json(out, &TreeFile {
+        lo: range.lo,
+        hi: range.hi,
+        leaves_len: tree.leaves_len(),
+        root: hex::encode(root),
+        leaf_encoding: encoding.id().to_string(),
+        leaves,
+    })?;
+
+    println!("root {} over {} leaves, wrote {}", hex::encode(root), tree.leaves_len(), out.display());
+    Ok(())
+}
+
+fn prove(witness: u64, issuer: &IssuerSource, tree_path: &Path, out: &Path, context: &str) -> CliResult<()> {
+    let (g, h, b) = read_params(issuer.params)?;
+    let keypair = read_issuer(issuer)?;
+    let tree_file: TreeFile = read_json(tree_path)?;
+    let encoding = leaf_encoding_from_id(&tree_file.leaf_encoding).map_err(|e| e.to_string())?;
+
+    // A root-only tree file is rebuilt from its range
+    let tree = match &tree_file.leaves {
+        Some(leaves) => {
+            let leaves = leaves
+                .iter()
+                .map(|leaf| hash_from_hex(leaf))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            IndexedTree::try_from_leaves(&leaves).map_err(|e| e.to_string())?
+        }
+        None => ValueRange::new(tree_file.lo, tree_file.hi)
+            .and_then(|range| tree_setup_range_with_encoding(range, &g, keypair.anchor(), keypair.secret(), encoding))
+            .map_err(|e| e.to_string())?,
+    };
+    if tree.root().map(hex::encode).as_deref() != Some(tree_file.root.as_str()) {
+        return Err(format!("{} does not match its recorded root", tree_path.display()));
+    }
+
+    let witness = Zeroizing::new(Fr::from(witness));
+    let blinding = secret_setup();
+
+    let proof: AnchoredProof = generate_anchored_proof(ProofInput {
+        secret: keypair.secret(),
+        witness: &witness,
+        blinding: &blinding,
+        generator_g: &g,
+        generator_h: &h,
+        generator_b: &b,
+        anchor: keypair.anchor(),
+        tree: &tree,
+        context: context.as_bytes(),
+        leaf_encoding: encoding,
+    })
+    .map_err(|e| e.to_string())?;
+
+    write_file(out, &proof_to_json(&proof))?;
+    println!("wrote {}", out.display());
+    Ok(())
+}
+
+/// Verifies against the tree file's root, which must use `encoding`
+fn verify(
+    proof_path: &Path,
+    params_path: &Path,
+    anchor_path: &Path,
+    tree_path: &Path,
+    context: &str,
+    encoding: LeafEncoding,
+) -> CliResult<()> {
+    let proof = read_proof(proof_path)?;
+    let (g, h, b) = read_params(params_path)?;
+    let anchor_file: AnchorFile = read_json(anchor_path)?;
+    let tree_file: TreeFile = read_json(tree_path)?;
+
+    // Legacy roots are only accepted when asked for on the command line
+    if leaf_encoding_from_id(&tree_file.leaf_encoding).map_err(|e| e.to_string())? != encoding {
+        return Err(format!(
+            "{} uses leaf encoding {}, not {} (--legacy-leaf-encoding selects legacy-x-only)",
+            tree_path.display(),
+            tree_file.leaf_encoding,
+            encoding.id()
+        ));
+    }
+
+    if proof.key_id != key_id_from_hex(&anchor_file.key_id).map_err(|e| e.to_string())? {
+        return Err(format!("proof was made under key {}, not {}", hex::encode(proof.key_id), anchor_file.key_id));
+    }
+
+    let context = VerificationContext {
+        generator_g: g,
+        generator_h: h,
+        generator_b: b,
+        anchor: point_from_hex(&anchor_file.anchor).map_err(|e| e.to_string())?,
+        tree_root: hash_from_hex(&tree_file.root).map_err(|e| e.to_string())?,
+        leaves_len: tree_file.leaves_len,
+        context: context.as_bytes().to_vec(),
+        leaf_encoding: encoding,
+    };
+
+    verify_anchored_proof(&proof, &context).map_err(|e| format!("proof rejected: {e}"))?;
+    println!("proof valid");
+    Ok(())
+}
+
+/// Audits the tree file against `claim`, or against the range it records
+fn audit(params_path: &Path, anchor_path: &Path, tree_path: &Path, claim: Option<TreeClaim<Fr>>) -> CliResult<()> {
+    let (g, _, b) = read_params(params_path)?;
+    let anchor_file: AnchorFile = read_json(anchor_path)?;
+    let tree_file: TreeFile = read_json(tree_path)?;
+    let claim = match claim {
+        Some(claim) => claim,
+        None => TreeClaim::Range(ValueRange::new(tree_file.lo, tree_file.hi).map_err(|e| e.to_string())?),
+    };
+
+    let anchor: G1Affine = point_from_hex(&anchor_file.anchor).map_err(|e| e.to_string())?;
+    if hex::encode(key_id(&anchor)) != anchor_file.key_id {
+        return Err(format!("{}: key id does not match the anchor", anchor_path.display()));
+    }
+    let tree_anchor: TreeAnchor = anchor_file
+        .tree_anchor
+        .as_ref()
+        .ok_or_else(|| format!("{} has no tree anchor", anchor_path.display()))
+        .and_then(|json| TreeAnchor::try_from(json).map_err(|e| e.to_string()))?;
+
+    let leaves = tree_file
+        .leaves
+        .as_ref()
+        .map(|leaves| leaves.iter().map(|leaf| hash_from_hex(leaf)).collect::<Result<Vec<_>, _>>())
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let published = PublishedTree {
+        root: hash_from_hex(&tree_file.root).map_err(|e| e.to_string())?,
+        leaves_len: tree_file.leaves_len,
+        leaves: leaves.as_deref(),
+        leaf_encoding: leaf_encoding_from_id(&tree_file.leaf_encoding).map_err(|e| e.to_string())?,
+    };
+
+    let report =
+        audit_tree(&g, &b, &anchor, &tree_anchor, &claim, &published).map_err(|e| format!("audit failed: {e}"))?;
+    println!("rebuilt root {} over {} leaves", hex::encode(report.rebuilt_root), report.rebuilt_leaves_len);
+    match report.outcome {
+        AuditOutcome::Match => {
+            println!("tree matches");
+            Ok(())
+        }
+        AuditOutcome::Mismatch { first_differing_leaf: Some(index) } => {
+            Err(format!("tree does not match: first differing leaf is {index}"))
+        }
+        AuditOutcome::Mismatch { first_differing_leaf: None } => {
+            Err(format!("tree does not match: published root {}", tree_file.root))
+        }
+    }
+}
+
+fn inspect(proof_path: &Path) -> CliResult<()> {
+    let proof = read_proof(proof_path)?;
+
+    println!("Anchored proof ({})", proof_path.display());
+    println!("  key id:                 {}", hex::encode(proof.key_id));
+    println!("  commitment C:           {}", point_to_hex(&proof.commitment));
+    println!("  modified commitment C': {}", point_to_hex(&proof.modified_commitment));
+    println!("  P point:                {}", point_to_hex(&proof.p_point));
+    match proof.leaf_hash {
+        Some(hash) => println!("  leaf hash:              {}", hex::encode(hash)),
+        None => println!("  leaf hash:              (omitted, recomputed by verifiers)"),
+    }
+    println!("  leaf index:             {}", proof.leaf_index);
+    println!("  merkle path ({} hashes):", proof.merkle_proof.proof_hashes().len());
+    for (level, hash) in proof.merkle_proof.proof_hashes().iter().enumerate() {
+        println!("    [{level}] {}", hex::encode(hash));
+    }
+    println!("  DLEQ proof:");
+    println!("    R1: {}", point_to_hex(&proof.dleq_proof.r_commitment_1));
+    println!("    R2: {}", point_to_hex(&proof.dleq_proof.r_commitment_2));
+    println!("    z:  {}", scalar_to_string(&proof.dleq_proof.response));
+    println!("  Schnorr proof:");
+    println!("    r:  {}", point_to_hex(&proof.schnorr_proof.commitment));
+    println!("    z:  {}", scalar_to_string(&proof.schnorr_proof.response));
+    Ok(())
+}
+
+fn read_params(path: &Path) -> CliResult<(G1Affine, G1Affine, G1Affine)> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let params = params_from_json(&contents).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(params.generators())
+}
+
+fn read_issuer(issuer: &IssuerSource) -> CliResult<IssuerKeypair> {
+    let (_, _, b) = read_params(issuer.params)?;
+    let passphrase = env_secret(issuer.passphrase_env)?;
+    let contents = fs::read_to_string(issuer.path).map_err(|e| format!("{}: {e}", issuer.path.display()))?;
+    issuer_key_from_json(&contents)
+        .and_then(|envelope| IssuerKeypair::import(&envelope, passphrase.as_bytes(), &b))
+        .map_err(|e| format!("{}: {e}", issuer.path.display()))
+}
+
+fn env_secret(name: &str) -> CliResult<Zeroizing<String>> {
+    std::env::var(name).map(Zeroizing::new).map_err(|_| format!("environment variable {name} is not set"))
+}
+
+fn read_proof(path: &Path) -> CliResult<AnchoredProof> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    proof_from_json(&contents).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> CliResult<T> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_str(&contents).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> CliResult<()> {
+    let contents = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    write_file(path, &contents)
+}
+
+fn write_file(path: &Path, contents: &str) -> CliResult<()> {
+    fs::write(path, contents).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Writes a file that must not exist yet, unless `force` allows replacing it
+fn create_file(path: &Path, contents: &str, force: bool) -> CliResult<()> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut file = options.open(path).map_err(|e| match e.kind() {
+        ErrorKind::AlreadyExists => already_exists(path),
+        _ => format!("{}: {e}", path.display()),
+    })?;
+    file.write_all(contents.as_bytes()).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn already_exists(path: &Path) -> String {
+    format!("{} already exists (pass --force to overwrite it)", path.display())
+}
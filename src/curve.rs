@@ -0,0 +1,128 @@
+//! Curves the protocol can run on
+//!
+//! Leaves and Fiat–Shamir challenges are Poseidon hashes over BN254 Fr, so a
+//! curve only needs to say how its x-coordinates become elements of that field
+//! and how a hash output becomes one of its scalars.
+//!
+//! Implementations are provided for BN254 G1 (the default type parameter of
+//! the proof types), BLS12-381 G1 and Baby Jubjub. Circom circuits should use
+//! Baby Jubjub, whose coordinates are native Poseidon field elements; the
+//! `babyjubjub` module supplies its generators and type aliases.
+
+use ark_bn254::Fr;
+use ark_ec::hashing::curve_maps::parity;
+use ark_ec::short_weierstrass::Projective;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::{AnchoredProofError, split_fq_to_fr};
+
+pub trait PoseidonCurve: CurveGroup {
+    /// Encodes the x-coordinate of `point` as Poseidon field elements
+    fn x_to_fields(point: &Self::Affine) -> Result<Vec<Fr>, AnchoredProofError>;
+
+    /// Canonical encoding of `point`: the x-coordinate limbs followed by the
+    /// parity of y, so the two points sharing an x-coordinate differ: P and -P
+    /// on short Weierstrass curves, P and T - P = (x, -y) on twisted Edwards
+    /// curves, where T = (0, -1) is the point of order 2
+    fn point_to_fields(point: &Self::Affine) -> Result<Vec<Fr>, AnchoredProofError> {
+        let mut fields = Self::x_to_fields(point)?;
+        let y = point.y().ok_or(AnchoredProofError::PointAtInfinity)?;
+        fields.push(Fr::from(parity(&y)));
+        Ok(fields)
+    }
+
+    /// Maps a Poseidon output to a challenge scalar
+    fn challenge_scalar(hash: &Fr) -> Self::ScalarField {
+        Self::ScalarField::from_le_bytes_mod_order(&hash.into_bigint().to_bytes_le())
+    }
+}
+
+/// BN254 G1: the 254-bit base field is split into two 128-bit limbs
+impl PoseidonCurve for Projective<ark_bn254::g1::Config> {
+    fn x_to_fields(point: &Self::Affine) -> Result<Vec<Fr>, AnchoredProofError> {
+        let x = point.x().ok_or(AnchoredProofError::PointAtInfinity)?;
+        Ok(split_fq_to_fr(&x))
+    }
+}
+
+/// BLS12-381 G1: the 381-bit base field is split into three 128-bit limbs
+impl PoseidonCurve for Projective<ark_bls12_381::g1::Config> {
+    fn x_to_fields(point: &Self::Affine) -> Result<Vec<Fr>, AnchoredProofError> {
+        let x = point.x().ok_or(AnchoredProofError::PointAtInfinity)?;
+        Ok(split_fq_to_fr(&x))
+    }
+}
+
+/// Baby Jubjub: the base field is BN254 Fr, so x is used as it is
+impl PoseidonCurve for ark_ed_on_bn254::EdwardsProjective {
+    fn x_to_fields(point: &Self::Affine) -> Result<Vec<Fr>, AnchoredProofError> {
+        let x = point.x().ok_or(AnchoredProofError::PointAtInfinity)?;
+        Ok(vec![x])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::{ValueRange, sample_nums_generator};
+    use crate::test_utils::Fixture;
+    use crate::verify::verify_anchored_proof;
+
+    // One run of the protocol, written once for every curve
+    fn prove_and_verify<C: PoseidonCurve>() {
+        let h: C::Affine = sample_nums_generator(&[0; 32]);
+        let b: C::Affine = sample_nums_generator(&[1; 32]);
+        let range = ValueRange::new(10, 20).unwrap();
+        let fixture = Fixture::<C>::with_generators((C::Affine::generator(), h, b), range);
+        let context = fixture.context();
+
+        let proof = fixture.prove(13);
+        assert_eq!(proof.leaf_index, range.index_of(13).unwrap());
+        assert_eq!(verify_anchored_proof(&proof, &context), Ok(()));
+        assert!(matches!(fixture.try_prove(21), Err(AnchoredProofError::WitnessOutOfRange)));
+    }
+
+    #[test]
+    fn test_protocol_runs_on_every_curve() {
+        prove_and_verify::<ark_bn254::G1Projective>();
+        prove_and_verify::<ark_bls12_381::G1Projective>();
+        prove_and_verify::<ark_ed_on_bn254::EdwardsProjective>();
+    }
+
+    #[test]
+    fn test_point_encoding_separates_negation() {
+        let point = ark_bn254::G1Affine::generator();
+        let encoded = ark_bn254::G1Projective::point_to_fields(&point).unwrap();
+        let negated = ark_bn254::G1Projective::point_to_fields(&-point).unwrap();
+        assert_eq!(encoded.len(), 3);
+        assert_eq!(encoded[..2], negated[..2]);
+        assert_ne!(encoded[2], negated[2]);
+    }
+
+    #[test]
+    fn test_edwards_encoding_separates_points_sharing_x() {
+        use ark_ed_on_bn254::{EdwardsAffine, Fq};
+        use ark_ff::{One, Zero};
+
+        let point = EdwardsAffine::generator();
+        let t = EdwardsAffine::new_unchecked(Fq::zero(), -Fq::one());
+        let shares_x = (t - point).into_affine();
+        assert_eq!((shares_x.x, shares_x.y), (point.x, -point.y));
+
+        let encoded = ark_ed_on_bn254::EdwardsProjective::point_to_fields(&point).unwrap();
+        let other = ark_ed_on_bn254::EdwardsProjective::point_to_fields(&shares_x).unwrap();
+        assert_eq!(encoded[0], other[0]);
+        assert_ne!(encoded[1], other[1]);
+    }
+
+    #[test]
+    fn test_bls12_381_limbs_cover_the_coordinate() {
+        let point = ark_bls12_381::G1Affine::generator();
+        let limbs = ark_bls12_381::G1Projective::x_to_fields(&point).unwrap();
+        assert_eq!(limbs.len(), 3);
+
+        let bytes: Vec<u8> = limbs.iter().flat_map(|limb| limb.into_bigint().to_bytes_le()[..16].to_vec()).collect();
+        assert_eq!(ark_bls12_381::Fq::from_le_bytes_mod_order(&bytes), point.x().unwrap());
+    }
+}
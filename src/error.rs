@@ -0,0 +1,67 @@
+use std::fmt;
+
+use light_poseidon::PoseidonError;
+
+/// Reasons an anchored proof cannot be generated or is rejected
+#[derive(Debug, PartialEq)]
+pub enum AnchoredProofError {
+    /// The witness does not map to any leaf of the tree
+    WitnessOutOfRange,
+    /// The requested tree range is empty, starts at 0 or is too large, or the
+    /// requested set is empty; a set containing 0 fails with `PointAtInfinity`
+    InvalidRange,
+    /// A point that must have affine coordinates is the identity
+    PointAtInfinity,
+    /// The Merkle path does not lead from the leaf to the tree root
+    MerkleMismatch,
+    /// The DLEQ proof between the anchor and the modified commitment does not hold
+    DleqFailed,
+    /// The Schnorr proof of the blinding term does not hold
+    SchnorrFailed,
+    /// Poseidon rejected its inputs
+    HashError(PoseidonError),
+    /// An encoded proof or context could not be decoded
+    InvalidEncoding(String),
+    /// Public parameters do not match their declared derivation
+    InvalidParameters(String),
+    /// An issuer key could not be derived, decrypted or matched to its key id
+    InvalidKey(String),
+    /// The proof was made under a different issuer key than the context's anchor
+    KeyIdMismatch,
+    /// A tree anchor does not share its discrete log with the issuer anchor
+    InvalidTreeAnchor,
+}
+
+impl fmt::Display for AnchoredProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnchoredProofError::WitnessOutOfRange => write!(f, "witness does not match any leaf of the tree"),
+            AnchoredProofError::InvalidRange => write!(f, "invalid tree range"),
+            AnchoredProofError::PointAtInfinity => write!(f, "unexpected point at infinity"),
+            AnchoredProofError::MerkleMismatch => write!(f, "merkle path does not match the tree root"),
+            AnchoredProofError::DleqFailed => write!(f, "DLEQ proof verification failed"),
+            AnchoredProofError::SchnorrFailed => write!(f, "Schnorr proof verification failed"),
+            AnchoredProofError::HashError(e) => write!(f, "poseidon hash error: {e}"),
+            AnchoredProofError::InvalidEncoding(e) => write!(f, "invalid encoding: {e}"),
+            AnchoredProofError::InvalidParameters(e) => write!(f, "invalid public parameters: {e}"),
+            AnchoredProofError::InvalidKey(e) => write!(f, "invalid issuer key: {e}"),
+            AnchoredProofError::KeyIdMismatch => write!(f, "proof key id does not match the anchor"),
+            AnchoredProofError::InvalidTreeAnchor => write!(f, "tree anchor proof verification failed"),
+        }
+    }
+}
+
+impl std::error::Error for AnchoredProofError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AnchoredProofError::HashError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PoseidonError> for AnchoredProofError {
+    fn from(e: PoseidonError) -> Self {
+        AnchoredProofError::HashError(e)
+    }
+}
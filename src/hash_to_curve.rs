@@ -0,0 +1,240 @@
+//! RFC 9380 hash-to-curve for BN254 G1
+//!
+//! Suite `BN254G1_XMD:SHA-256_SVDW_RO_`: `expand_message_xmd` with SHA-256,
+//! `hash_to_field` with L = 48, the Shallue–van de Woestijne map with Z = 1
+//! (RFC 9380 section 6.6.1) and the random-oracle construction, which maps two
+//! field elements and adds the points. BN254 G1 has cofactor 1.
+//!
+//! The generators H and B are `hash_to_curve(GENERATOR_DST, "H")` and
+//! `hash_to_curve(GENERATOR_DST, "B")`. Anyone can recompute them with an
+//! independent RFC 9380 implementation, and since they are random-oracle
+//! outputs nobody knows their discrete logarithms relative to G or each other:
+//!
+//! | | x | y |
+//! |---|---|---|
+//! | H | `12f85fe15bee5e4901174557b4877042138f10388398bdb78e71949fafe939d0` | `03bddb228fc4862d43f10190ee31dfffc5debe5330adb87ca4c6c5590b704871` |
+//! | B | `106835902586b28e8fdb359b75bb9f34a3d8e887362500dac66d5aba15e7de04` | `115b2fbcb65dbe80dc3998d1a21b04fbd6662a80e393e6d1467c38171f00128e` |
+//!
+//! Coordinates are big-endian hex.
+
+use ark_bn254::{Fq, G1Affine};
+use ark_ec::hashing::curve_maps::parity;
+use ark_ec::short_weierstrass::SWCurveConfig;
+use ark_ec::CurveGroup;
+use ark_ff::{Field, One, PrimeField, Zero};
+use sha2::{Digest, Sha256};
+
+/// Domain-separation tag for the protocol generators, in the RFC 9380
+/// section 3.1 format `<application>-V<version>-CS<ciphersuite>-with-<suite ID>`
+pub const GENERATOR_DST: &str = "ANCHORED-MERKLE-PROOF-V01-CS01-with-BN254G1_XMD:SHA-256_SVDW_RO_";
+
+pub const GENERATOR_H_MESSAGE: &str = "H";
+pub const GENERATOR_B_MESSAGE: &str = "B";
+
+/// ceil((ceil(log2(p)) + k) / 8) for k = 128
+const FIELD_ELEMENT_LEN: usize = 48;
+
+/// SHA-256 input block size (s_in_bytes)
+const SHA256_BLOCK_LEN: usize = 64;
+
+const Z: Fq = Fq::ONE;
+
+/// `hash_to_curve` of RFC 9380 with the `BN254G1_XMD:SHA-256_SVDW_RO_` suite
+pub fn hash_to_g1(dst: &[u8], msg: &[u8]) -> G1Affine {
+    let [u0, u1] = hash_to_field(dst, msg);
+    (map_to_curve(u0) + map_to_curve(u1)).into_affine()
+}
+
+/// `hash_to_field` of RFC 9380 section 5.2 with count = 2
+pub fn hash_to_field(dst: &[u8], msg: &[u8]) -> [Fq; 2] {
+    let bytes = expand_message_xmd(dst, msg, 2 * FIELD_ELEMENT_LEN);
+    let (first, second) = bytes.split_at(FIELD_ELEMENT_LEN);
+    [Fq::from_be_bytes_mod_order(first), Fq::from_be_bytes_mod_order(second)]
+}
+
+/// `expand_message_xmd` of RFC 9380 section 5.3.1 with SHA-256
+///
+/// Not `ark_ff::field_hashers::DefaultFieldHasher`: its 0.5 release pads with
+/// the field element length instead of the hash block size, which only agrees
+/// with the RFC when the two happen to be equal (BLS12-381, not BN254).
+fn expand_message_xmd(dst: &[u8], msg: &[u8], len_in_bytes: usize) -> Vec<u8> {
+    // Section 5.3.3: tags over 255 bytes are hashed down first
+    let oversize_dst;
+    let dst = if dst.len() > 255 {
+        oversize_dst = Sha256::new().chain_update(b"H2C-OVERSIZE-DST-").chain_update(dst).finalize();
+        &oversize_dst[..]
+    } else {
+        dst
+    };
+    let ell = len_in_bytes.div_ceil(32);
+    assert!(ell <= 255 && len_in_bytes <= u16::MAX as usize, "expand_message_xmd output too long");
+
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+    let b_0 = Sha256::new()
+        .chain_update([0u8; SHA256_BLOCK_LEN])
+        .chain_update(msg)
+        .chain_update((len_in_bytes as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+
+    let mut b_i = Sha256::new().chain_update(b_0).chain_update([1u8]).chain_update(&dst_prime).finalize();
+    let mut uniform_bytes = b_i.to_vec();
+    for i in 2..=ell {
+        let xored: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(a, b)| a ^ b).collect();
+        b_i = Sha256::new().chain_update(xored).chain_update([i as u8]).chain_update(&dst_prime).finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len_in_bytes);
+    uniform_bytes
+}
+
+/// g(x) = x^3 + A*x + B
+fn curve_rhs(x: Fq) -> Fq {
+    x.square() * x + ark_bn254::g1::Config::COEFF_A * x + ark_bn254::g1::Config::COEFF_B
+}
+
+/// 3*Z^2 + 4*A
+fn svdw_denominator() -> Fq {
+    Z.square() * Fq::from(3u64) + ark_bn254::g1::Config::COEFF_A * Fq::from(4u64)
+}
+
+/// The constants c1 to c4 of RFC 9380 appendix F.1
+fn svdw_constants() -> (Fq, Fq, Fq, Fq) {
+    let gz = curve_rhs(Z);
+
+    let c1 = gz;
+    let c2 = -Z / Fq::from(2u64);
+    let c3 = (-gz * svdw_denominator()).sqrt().expect("Z satisfies the SVDW criteria");
+    let c3 = if parity(&c3) { -c3 } else { c3 };
+    let c4 = -gz * Fq::from(4u64) / svdw_denominator();
+    (c1, c2, c3, c4)
+}
+
+/// The Shallue–van de Woestijne map for BN254 G1 (y^2 = x^3 + 3) with Z = 1
+///
+/// Straight-line implementation of RFC 9380 appendix F.1. Generator inputs are
+/// public, so the conditional moves are plain branches.
+pub fn map_to_curve(u: Fq) -> G1Affine {
+    let (c1, c2, c3, c4) = svdw_constants();
+
+    let tv1 = u.square() * c1;
+    let tv2 = Fq::one() + tv1;
+    let tv1 = Fq::one() - tv1;
+    let tv3 = (tv1 * tv2).inverse().unwrap_or(Fq::zero());
+    let tv4 = u * tv1 * tv3 * c3;
+
+    let x1 = c2 - tv4;
+    let x2 = c2 + tv4;
+    let x3 = (tv2.square() * tv3).square() * c4 + Z;
+
+    let x = if curve_rhs(x1).sqrt().is_some() {
+        x1
+    } else if curve_rhs(x2).sqrt().is_some() {
+        x2
+    } else {
+        x3
+    };
+
+    let y = curve_rhs(x).sqrt().expect("the SVDW map always lands on the curve");
+    let y = if parity(&u) == parity(&y) { y } else { -y };
+    G1Affine::new_unchecked(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_from_hex(x: &str, y: &str) -> G1Affine {
+        let coordinate = |s: &str| Fq::from_be_bytes_mod_order(&hex::decode(s).unwrap());
+        G1Affine::new(coordinate(x), coordinate(y))
+    }
+
+    // RFC 9380 appendix K.1, expand_message_xmd(SHA-256) with 32 output bytes
+    #[test]
+    fn test_expand_message_xmd_vectors() {
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        let vectors = [
+            ("", "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"),
+            ("abc", "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"),
+            ("abcdef0123456789", "eff31487c770a893cfb36f912fbfcbff40d5661771ca4b2cb4eafe524333f5c1"),
+        ];
+        for (msg, expected) in vectors {
+            assert_eq!(hex::encode(expand_message_xmd(dst, msg.as_bytes(), 32)), expected, "message {msg:?}");
+        }
+    }
+
+    // The criteria on Z from RFC 9380 section 6.6.1
+    #[test]
+    fn test_z_satisfies_svdw_criteria() {
+        let gz = curve_rhs(Z);
+        let h = -svdw_denominator() / (gz * Fq::from(4u64));
+        assert!(!gz.is_zero());
+        assert!(!h.is_zero() && h.sqrt().is_some());
+        assert!(gz.sqrt().is_some() || curve_rhs(-Z / Fq::from(2u64)).sqrt().is_some());
+    }
+
+    // The suite's reference vectors, as published with gnark-crypto
+    // (ecc/bn254/hash_vectors_test.go)
+    #[test]
+    fn test_suite_vectors() {
+        let dst = b"QUUX-V01-CS02-with-BN254G1_XMD:SHA-256_SVDW_RO_";
+        let vectors = [
+            (
+                "",
+                "0a976ab906170db1f9638d376514dbf8c42aef256a54bbd48521f20749e59e86",
+                "02925ead66b9e68bfc309b014398640ab55f6619ab59bc1fab2210ad4c4d53d5",
+            ),
+            (
+                "abc",
+                "23f717bee89b1003957139f193e6be7da1df5f1374b26a4643b0378b5baf53d1",
+                "04142f826b71ee574452dbc47e05bc3e1a647478403a7ba38b7b93948f4e151d",
+            ),
+            (
+                "abcdef0123456789",
+                "187dbf1c3c89aceceef254d6548d7163fdfa43084145f92c4c91c85c21442d4a",
+                "0abd99d5b0000910b56058f9cc3b0ab0a22d47cf27615f588924fac1e5c63b4d",
+            ),
+        ];
+
+        for (msg, x, y) in vectors {
+            assert_eq!(hash_to_g1(dst, msg.as_bytes()), point_from_hex(x, y), "message {msg:?}");
+        }
+
+        let q128 = format!("q128_{}", "q".repeat(128));
+        assert_eq!(
+            hash_to_g1(dst, q128.as_bytes()),
+            point_from_hex(
+                "00fe2b0743575324fc452d590d217390ad48e5a16cf051bee5c40a2eba233f5c",
+                "0794211e0cc72d3cbbdf8e4e5cd6e7d7e78d101ff94862caae8acbe63e9fdc78"
+            )
+        );
+        let a512 = format!("a512_{}", "a".repeat(512));
+        assert_eq!(
+            hash_to_g1(dst, a512.as_bytes()),
+            point_from_hex(
+                "01b05dc540bd79fd0fea4fbb07de08e94fc2e7bd171fe025c479dc212a2173ce",
+                "1bf028afc00c0f843d113758968f580640541728cfc6d32ced9779aa613cd9b0"
+            )
+        );
+    }
+
+    // The published generators from the module documentation
+    #[test]
+    fn test_generator_vectors() {
+        assert_eq!(
+            hash_to_g1(GENERATOR_DST.as_bytes(), GENERATOR_H_MESSAGE.as_bytes()),
+            point_from_hex(
+                "12f85fe15bee5e4901174557b4877042138f10388398bdb78e71949fafe939d0",
+                "03bddb228fc4862d43f10190ee31dfffc5debe5330adb87ca4c6c5590b704871"
+            )
+        );
+        assert_eq!(
+            hash_to_g1(GENERATOR_DST.as_bytes(), GENERATOR_B_MESSAGE.as_bytes()),
+            point_from_hex(
+                "106835902586b28e8fdb359b75bb9f34a3d8e887362500dac66d5aba15e7de04",
+                "115b2fbcb65dbe80dc3998d1a21b04fbd6662a80e393e6d1467c38171f00128e"
+            )
+        );
+    }
+}
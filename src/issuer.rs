@@ -0,0 +1,348 @@
+//! Issuer keys: the secret s and its anchor U = B*s
+//!
+//! A keypair is either random or derived from a master seed and a label, so
+//! one seed can back several key epochs ("2025", "2026", ...). Its key id is a
+//! hash of the anchor; proofs carry it so a verifier that trusts several
+//! anchors knows which one to check against. For storage the secret is
+//! exported encrypted under a passphrase (Argon2id, then ChaCha20-Poly1305).
+//!
+//! Verification rejects a proof whose key id does not match the context's
+//! anchor; `select_anchor` finds the anchor for a key id among trusted ones.
+
+use ark_bn254::G1Projective;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{PrimeField, UniformRand, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+use hmac::{Hmac, Mac};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::AnchoredProofError;
+
+/// SHA-256 of the compressed anchor under `KEY_ID_LABEL`
+pub type KeyId = [u8; 32];
+
+pub const KEY_ID_LABEL: &[u8] = b"anchored-merkle-proof/key-id/v1";
+pub const KEY_DERIVATION_LABEL: &[u8] = b"anchored-merkle-proof/issuer-key/v1";
+
+/// Current version of the `EncryptedIssuerKey` format
+pub const ISSUER_KEY_ENCODING_VERSION: u8 = 1;
+
+/// Shortest master seed `IssuerKeypair::derive` accepts
+pub const MIN_SEED_LEN: usize = 32;
+
+/// Issuer secret and anchor over the curve `C`; the secret is wiped on drop
+pub struct IssuerKeypair<C: CurveGroup = G1Projective> {
+    secret: Zeroizing<C::ScalarField>,
+    anchor: C::Affine,
+    key_id: KeyId,
+}
+
+impl<C: CurveGroup> IssuerKeypair<C> {
+    pub fn from_secret(secret: Zeroizing<C::ScalarField>, generator_b: &C::Affine) -> Result<Self, AnchoredProofError> {
+        if secret.is_zero() {
+            return Err(AnchoredProofError::InvalidKey("zero secret".to_string()));
+        }
+        let anchor = (*generator_b * *secret).into_affine();
+        Ok(IssuerKeypair { secret, anchor, key_id: key_id(&anchor) })
+    }
+
+    pub fn generate<R: RngCore + CryptoRng>(generator_b: &C::Affine, rng: &mut R) -> Self {
+        loop {
+            if let Ok(keypair) = Self::from_secret(Zeroizing::new(C::ScalarField::rand(rng)), generator_b) {
+                return keypair;
+            }
+        }
+    }
+
+    /// Deterministic keypair for `label`, e.g. a key epoch, under `master_seed`
+    ///
+    /// The secret is HMAC-SHA512(master_seed, KEY_DERIVATION_LABEL || len || label
+    /// || counter) reduced mod r, with the counter starting at 0 and only
+    /// incremented if the result is zero.
+    pub fn derive(master_seed: &[u8], label: &str, generator_b: &C::Affine) -> Result<Self, AnchoredProofError> {
+        if master_seed.len() < MIN_SEED_LEN {
+            return Err(AnchoredProofError::InvalidKey(format!("master seed shorter than {MIN_SEED_LEN} bytes")));
+        }
+
+        for counter in 0u8..=u8::MAX {
+            let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(master_seed).expect("HMAC accepts keys of any length");
+            mac.update(KEY_DERIVATION_LABEL);
+            mac.update(&(label.len() as u64).to_be_bytes());
+            mac.update(label.as_bytes());
+            mac.update(&[counter]);
+            let mut bytes = mac.finalize().into_bytes();
+
+            let secret = Zeroizing::new(C::ScalarField::from_be_bytes_mod_order(&bytes));
+            bytes.zeroize();
+            if let Ok(keypair) = Self::from_secret(secret, generator_b) {
+                return Ok(keypair);
+            }
+        }
+        unreachable!("256 consecutive zero scalars from HMAC-SHA512")
+    }
+
+    pub fn secret(&self) -> &Zeroizing<C::ScalarField> {
+        &self.secret
+    }
+
+    pub fn anchor(&self) -> &C::Affine {
+        &self.anchor
+    }
+
+    pub fn key_id(&self) -> KeyId {
+        self.key_id
+    }
+
+    /// Encrypts the secret under `passphrase` with the default Argon2id cost
+    pub fn export<R: RngCore + CryptoRng>(
+        &self,
+        passphrase: &[u8],
+        rng: &mut R,
+    ) -> Result<EncryptedIssuerKey, AnchoredProofError> {
+        self.export_with_kdf(passphrase, KdfParams::default(), rng)
+    }
+
+    pub fn export_with_kdf<R: RngCore + CryptoRng>(
+        &self,
+        passphrase: &[u8],
+        kdf: KdfParams,
+        rng: &mut R,
+    ) -> Result<EncryptedIssuerKey, AnchoredProofError> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+
+        let mut plaintext = Zeroizing::new(Vec::new());
+        self.secret
+            .serialize_compressed(&mut *plaintext)
+            .expect("serializing into a Vec cannot fail");
+
+        let mut envelope = EncryptedIssuerKey {
+            version: ISSUER_KEY_ENCODING_VERSION,
+            key_id: self.key_id,
+            kdf,
+            salt,
+            nonce,
+            ciphertext: Vec::new(),
+        };
+        let cipher = envelope.cipher(passphrase)?;
+        envelope.ciphertext = cipher
+            .encrypt(&nonce.into(), Payload { msg: &plaintext, aad: &envelope.header() })
+            .expect("ChaCha20-Poly1305 encryption cannot fail on short inputs");
+        Ok(envelope)
+    }
+
+    /// Decrypts an exported key and checks that it belongs to its recorded key id
+    pub fn import(
+        envelope: &EncryptedIssuerKey,
+        passphrase: &[u8],
+        generator_b: &C::Affine,
+    ) -> Result<Self, AnchoredProofError> {
+        if envelope.version != ISSUER_KEY_ENCODING_VERSION {
+            return Err(AnchoredProofError::InvalidKey(format!("unsupported version {}", envelope.version)));
+        }
+
+        let cipher = envelope.cipher(passphrase)?;
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(&envelope.nonce.into(), Payload { msg: &envelope.ciphertext, aad: &envelope.header() })
+                .map_err(|_| AnchoredProofError::InvalidKey("wrong passphrase or corrupted key".to_string()))?,
+        );
+        let secret = Zeroizing::new(
+            C::ScalarField::deserialize_compressed(&plaintext[..])
+                .map_err(|e| AnchoredProofError::InvalidKey(format!("secret: {e}")))?,
+        );
+
+        let keypair = Self::from_secret(secret, generator_b)?;
+        if keypair.key_id != envelope.key_id {
+            return Err(AnchoredProofError::InvalidKey("key does not match its key id".to_string()));
+        }
+        Ok(keypair)
+    }
+}
+
+/// Largest Argon2id memory cost accepted, 4x the default: an imported envelope
+/// sets its own cost, which Argon2 spends before the ciphertext is authenticated
+pub const MAX_KDF_MEMORY_KIB: u32 = 4 * 19 * 1024;
+
+/// Largest Argon2id pass count accepted, for the same reason
+pub const MAX_KDF_ITERATIONS: u32 = 16;
+
+/// Argon2id cost parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+}
+
+impl Default for KdfParams {
+    /// The OWASP recommendation for Argon2id: 19 MiB, two passes
+    fn default() -> Self {
+        KdfParams { memory_kib: 19 * 1024, iterations: 2 }
+    }
+}
+
+/// An issuer secret encrypted under a passphrase
+///
+/// The version, key id and KDF parameters are authenticated as associated
+/// data, so none of them can be swapped without failing decryption.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedIssuerKey {
+    pub version: u8,
+    pub key_id: KeyId,
+    pub kdf: KdfParams,
+    pub salt: [u8; 16],
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedIssuerKey {
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![self.version];
+        header.extend_from_slice(&self.key_id);
+        header.extend_from_slice(&self.kdf.memory_kib.to_be_bytes());
+        header.extend_from_slice(&self.kdf.iterations.to_be_bytes());
+        header
+    }
+
+    fn cipher(&self, passphrase: &[u8]) -> Result<ChaCha20Poly1305, AnchoredProofError> {
+        if self.kdf.memory_kib > MAX_KDF_MEMORY_KIB || self.kdf.iterations > MAX_KDF_ITERATIONS {
+            return Err(AnchoredProofError::InvalidKey(format!(
+                "KDF cost above the limit of {MAX_KDF_MEMORY_KIB} KiB and {MAX_KDF_ITERATIONS} passes"
+            )));
+        }
+        let params = Params::new(self.kdf.memory_kib, self.kdf.iterations, 1, Some(32))
+            .map_err(|e| AnchoredProofError::InvalidKey(format!("KDF parameters: {e}")))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase, &self.salt, &mut *key)
+            .map_err(|e| AnchoredProofError::InvalidKey(format!("KDF: {e}")))?;
+        Ok(ChaCha20Poly1305::new((&*key).into()))
+    }
+}
+
+/// Key id of the issuer whose anchor is `anchor`
+pub fn key_id<A: AffineRepr>(anchor: &A) -> KeyId {
+    let mut encoded = Vec::new();
+    anchor
+        .serialize_compressed(&mut encoded)
+        .expect("serializing into a Vec cannot fail");
+    Sha256::new().chain_update(KEY_ID_LABEL).chain_update(encoded).finalize().into()
+}
+
+/// The anchor among `anchors` whose key id is `id`
+pub fn select_anchor<'a, A: AffineRepr>(anchors: &'a [A], id: &KeyId) -> Option<&'a A> {
+    anchors.iter().find(|anchor| key_id(*anchor) == *id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fr, G1Affine};
+    use rand::rngs::OsRng;
+    use crate::setup::{anchor_setup, generator_setup};
+
+    const SEED: [u8; 32] = [7; 32];
+
+    // Cheap enough for debug-build tests
+    const TEST_KDF: KdfParams = KdfParams { memory_kib: 64, iterations: 1 };
+
+    fn generator_b() -> G1Affine {
+        generator_setup().2
+    }
+
+    #[test]
+    fn test_derivation_is_deterministic_per_label() {
+        let b = generator_b();
+        let first: IssuerKeypair = IssuerKeypair::derive(&SEED, "2025", &b).unwrap();
+        let again: IssuerKeypair = IssuerKeypair::derive(&SEED, "2025", &b).unwrap();
+        let next: IssuerKeypair = IssuerKeypair::derive(&SEED, "2026", &b).unwrap();
+
+        assert_eq!(first.secret(), again.secret());
+        assert_eq!(first.key_id(), again.key_id());
+        assert_ne!(first.secret(), next.secret());
+        assert_ne!(first.key_id(), next.key_id());
+        assert_eq!(*first.anchor(), anchor_setup(first.secret(), &b));
+
+        assert!(matches!(
+            IssuerKeypair::<G1Projective>::derive(&SEED[..16], "2025", &b),
+            Err(AnchoredProofError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let b = generator_b();
+        let keypair: IssuerKeypair = IssuerKeypair::generate(&b, &mut OsRng);
+        let envelope = keypair.export_with_kdf(b"correct horse", TEST_KDF, &mut OsRng).unwrap();
+        assert_eq!(envelope.key_id, keypair.key_id());
+
+        let imported: IssuerKeypair = IssuerKeypair::import(&envelope, b"correct horse", &b).unwrap();
+        assert_eq!(imported.secret(), keypair.secret());
+        assert_eq!(imported.anchor(), keypair.anchor());
+
+        let no_memory = KdfParams { memory_kib: 0, iterations: 1 };
+        assert!(matches!(keypair.export_with_kdf(b"correct horse", no_memory, &mut OsRng), Err(AnchoredProofError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_import_rejects_excessive_kdf_cost() {
+        let b = generator_b();
+        let keypair: IssuerKeypair = IssuerKeypair::generate(&b, &mut OsRng);
+        let envelope = keypair.export_with_kdf(b"correct horse", TEST_KDF, &mut OsRng).unwrap();
+        let rejected = |kdf: KdfParams| {
+            let crafted = EncryptedIssuerKey { kdf, ..envelope.clone() };
+            matches!(IssuerKeypair::<G1Projective>::import(&crafted, b"correct horse", &b), Err(AnchoredProofError::InvalidKey(_)))
+        };
+
+        // Rejected before Argon2 runs, so these return at once
+        assert!(rejected(KdfParams { memory_kib: u32::MAX, iterations: 1 }));
+        assert!(rejected(KdfParams { memory_kib: MAX_KDF_MEMORY_KIB + 1, iterations: 1 }));
+        assert!(rejected(KdfParams { memory_kib: 64, iterations: u32::MAX }));
+
+        let too_costly = KdfParams { memory_kib: MAX_KDF_MEMORY_KIB + 1, iterations: 1 };
+        assert!(matches!(keypair.export_with_kdf(b"correct horse", too_costly, &mut OsRng), Err(AnchoredProofError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_import_rejects_wrong_passphrase_and_tampering() {
+        let b = generator_b();
+        let keypair: IssuerKeypair = IssuerKeypair::generate(&b, &mut OsRng);
+        let envelope = keypair.export_with_kdf(b"correct horse", TEST_KDF, &mut OsRng).unwrap();
+        let rejected = |envelope: &EncryptedIssuerKey, passphrase: &[u8]| {
+            matches!(IssuerKeypair::<G1Projective>::import(envelope, passphrase, &b), Err(AnchoredProofError::InvalidKey(_)))
+        };
+
+        assert!(rejected(&envelope, b"battery staple"));
+
+        let mut tampered = envelope.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(rejected(&tampered, b"correct horse"));
+
+        // The key id is authenticated, so it cannot be relabelled
+        let mut relabelled = envelope.clone();
+        relabelled.key_id = key_id(&anchor_setup(&Zeroizing::new(Fr::from(3u64)), &b));
+        assert!(rejected(&relabelled, b"correct horse"));
+
+        let mut cheaper = envelope;
+        cheaper.kdf.iterations += 1;
+        assert!(rejected(&cheaper, b"correct horse"));
+    }
+
+    #[test]
+    fn test_select_anchor_by_key_id() {
+        let b = generator_b();
+        let keys: Vec<IssuerKeypair> =
+            ["2024", "2025", "2026"].iter().map(|label| IssuerKeypair::derive(&SEED, label, &b).unwrap()).collect();
+        let anchors: Vec<G1Affine> = keys.iter().map(|key| *key.anchor()).collect();
+
+        assert_eq!(select_anchor(&anchors, &keys[1].key_id()), Some(&anchors[1]));
+        assert_eq!(select_anchor(&anchors, &[0; 32]), None);
+    }
+}
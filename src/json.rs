@@ -0,0 +1,653 @@
+//! Human-readable JSON encoding of proofs and verification contexts
+//!
+//! Proofs, contexts and tree anchors convert for any `PoseidonCurve`, with the
+//! same schema on every curve: points are hex strings of their compressed
+//! arkworks encoding (32 bytes for BN254 G1 and Baby Jubjub, 48 for BLS12-381
+//! G1), scalars are decimal strings in the curve's scalar field and hashes are
+//! 32-byte hex strings. The JSON does not name the curve, so both ends must
+//! agree on it. Public parameters are BN254-only, like `PublicParameters`.
+//!
+//! Proof schema:
+//!
+//! ```json
+//! {
+//!   "version": 3,
+//!   "key_id": "<hex hash>",
+//!   "commitment": "<hex point>",
+//!   "modified_commitment": "<hex point>",
+//!   "p_point": "<hex point>",
+//!   "leaf_hash": "<hex hash, optional>",
+//!   "merkle_path": { "leaf_index": 5, "hashes": ["<hex hash>", "..."] },
+//!   "dleq_proof": {
+//!     "r_commitment_1": "<hex point>",
+//!     "r_commitment_2": "<hex point>",
+//!     "response": "<decimal scalar>"
+//!   },
+//!   "schnorr_proof": { "commitment": "<hex point>", "response": "<decimal scalar>" }
+//! }
+//! ```
+//!
+//! `"leaf_hash"` is optional as of version 3; the verifier recomputes the leaf
+//! and only compares it against a hash carried by the proof.
+//!
+//! Verification context schema:
+//!
+//! ```json
+//! {
+//!   "generator_g": "<hex point>",
+//!   "generator_h": "<hex point>",
+//!   "generator_b": "<hex point>",
+//!   "anchor": "<hex point>",
+//!   "tree_root": "<hex hash>",
+//!   "leaves_len": 256,
+//!   "context": "<hex bytes>",
+//!   "leaf_encoding": "full"
+//! }
+//! ```
+//!
+//! `leaf_encoding` is `"full"` or `"legacy-x-only"` and defaults to `"full"`;
+//! legacy roots must say so explicitly.
+//!
+//! Public parameters schema, where a null label marks the curve's standard
+//! generator:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "curve_id": "bn254-g1",
+//!   "poseidon_id": "poseidon-circom-bn254-x5",
+//!   "dst": "<hash-to-curve tag>",
+//!   "generator_g": { "label": null, "point": "<hex point>" },
+//!   "generator_h": { "label": "H", "point": "<hex point>" },
+//!   "generator_b": { "label": "B", "point": "<hex point>" }
+//! }
+//! ```
+//!
+//! Encrypted issuer key schema:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "key_id": "<hex hash>",
+//!   "kdf": { "memory_kib": 19456, "iterations": 2 },
+//!   "salt": "<16 hex bytes>",
+//!   "nonce": "<12 hex bytes>",
+//!   "ciphertext": "<hex>"
+//! }
+//! ```
+
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use rs_merkle::MerkleProof;
+use serde::{Deserialize, Serialize};
+
+use crate::issuer::{EncryptedIssuerKey, KdfParams};
+use crate::params::{Generator, PublicParameters};
+use crate::serialize::PROOF_ENCODING_VERSION;
+use crate::verify::VerificationContext;
+use crate::{
+    AnchoredProof, AnchoredProofError, DLEQProof, KeyId, LeafEncoding, PoseidonCurve, SchnorrProof, TreeAnchor,
+    is_canonical_hash,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnchoredProofJson {
+    pub version: u8,
+    pub key_id: String,
+    pub commitment: String,
+    pub modified_commitment: String,
+    pub p_point: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf_hash: Option<String>,
+    pub merkle_path: MerklePathJson,
+    pub dleq_proof: DleqProofJson,
+    pub schnorr_proof: SchnorrProofJson,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MerklePathJson {
+    pub leaf_index: usize,
+    pub hashes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DleqProofJson {
+    pub r_commitment_1: String,
+    pub r_commitment_2: String,
+    pub response: String,
+}
+
+/// A tree anchor A_G with its DLEQ proof against the issuer anchor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TreeAnchorJson {
+    pub point: String,
+    pub proof: DleqProofJson,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchnorrProofJson {
+    pub commitment: String,
+    pub response: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VerificationContextJson {
+    pub generator_g: String,
+    pub generator_h: String,
+    pub generator_b: String,
+    pub anchor: String,
+    pub tree_root: String,
+    pub leaves_len: usize,
+    pub context: String,
+    /// `LeafEncoding::id` of the tree the root was computed over
+    #[serde(default = "default_leaf_encoding_id")]
+    pub leaf_encoding: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PublicParametersJson {
+    pub version: u8,
+    pub curve_id: String,
+    pub poseidon_id: String,
+    pub dst: String,
+    pub generator_g: GeneratorJson,
+    pub generator_h: GeneratorJson,
+    pub generator_b: GeneratorJson,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GeneratorJson {
+    pub label: Option<String>,
+    pub point: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EncryptedIssuerKeyJson {
+    pub version: u8,
+    pub key_id: String,
+    pub kdf: KdfParamsJson,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KdfParamsJson {
+    pub memory_kib: u32,
+    pub iterations: u32,
+}
+
+pub fn point_to_hex<A: AffineRepr>(point: &A) -> String {
+    let mut bytes = Vec::new();
+    point
+        .serialize_compressed(&mut bytes)
+        .expect("serializing into a Vec cannot fail");
+    hex::encode(bytes)
+}
+
+/// Decodes a compressed point, rejecting invalid encodings and the identity
+pub fn point_from_hex<A: AffineRepr>(s: &str) -> Result<A, AnchoredProofError> {
+    let bytes = hex::decode(s).map_err(|e| AnchoredProofError::InvalidEncoding(format!("point: {e}")))?;
+    let point = A::deserialize_compressed(&bytes[..])
+        .map_err(|e| AnchoredProofError::InvalidEncoding(format!("point: {e}")))?;
+    if point.is_zero() {
+        return Err(AnchoredProofError::PointAtInfinity);
+    }
+    Ok(point)
+}
+
+pub fn scalar_to_string<F: PrimeField>(scalar: &F) -> String {
+    scalar.to_string()
+}
+
+/// Parses a canonical decimal scalar
+///
+/// `F::from_str` accepts signs and reduces mod r, so the value is re-encoded and
+/// compared to keep exactly one valid string per scalar.
+pub fn scalar_from_str<F: PrimeField>(s: &str) -> Result<F, AnchoredProofError> {
+    match F::from_str(s) {
+        Ok(scalar) if scalar.to_string() == s => Ok(scalar),
+        _ => Err(AnchoredProofError::InvalidEncoding(format!("scalar: {s:?}"))),
+    }
+}
+
+/// Encoding assumed when `leaf_encoding` is missing; x-only leaves are never implied
+pub fn default_leaf_encoding_id() -> String {
+    LeafEncoding::default().id().to_string()
+}
+
+pub fn leaf_encoding_from_id(id: &str) -> Result<LeafEncoding, AnchoredProofError> {
+    LeafEncoding::from_id(id).ok_or_else(|| AnchoredProofError::InvalidEncoding(format!("unknown leaf encoding {id:?}")))
+}
+
+/// Decodes a Poseidon hash, rejecting encodings of values at or above the field modulus
+pub fn hash_from_hex(s: &str) -> Result<[u8; 32], AnchoredProofError> {
+    let hash = bytes_from_hex(s, "hash")?;
+    if !is_canonical_hash(&hash) {
+        return Err(AnchoredProofError::InvalidEncoding("hash: not below the field modulus".to_string()));
+    }
+    Ok(hash)
+}
+
+pub fn key_id_from_hex(s: &str) -> Result<KeyId, AnchoredProofError> {
+    bytes_from_hex(s, "key_id")
+}
+
+fn bytes_from_hex<const N: usize>(s: &str, field: &str) -> Result<[u8; N], AnchoredProofError> {
+    let bytes = hex::decode(s).map_err(|e| AnchoredProofError::InvalidEncoding(format!("{field}: {e}")))?;
+    bytes
+        .try_into()
+        .map_err(|_| AnchoredProofError::InvalidEncoding(format!("{field}: expected {N} bytes")))
+}
+
+impl<C: PoseidonCurve> From<&AnchoredProof<C>> for AnchoredProofJson {
+    fn from(proof: &AnchoredProof<C>) -> Self {
+        AnchoredProofJson {
+            version: PROOF_ENCODING_VERSION,
+            key_id: hex::encode(proof.key_id),
+            commitment: point_to_hex(&proof.commitment),
+            modified_commitment: point_to_hex(&proof.modified_commitment),
+            p_point: point_to_hex(&proof.p_point),
+            leaf_hash: proof.leaf_hash.map(hex::encode),
+            merkle_path: MerklePathJson {
+                leaf_index: proof.leaf_index,
+                hashes: proof.merkle_proof.proof_hashes().iter().map(hex::encode).collect(),
+            },
+            dleq_proof: DleqProofJson {
+                r_commitment_1: point_to_hex(&proof.dleq_proof.r_commitment_1),
+                r_commitment_2: point_to_hex(&proof.dleq_proof.r_commitment_2),
+                response: scalar_to_string(&proof.dleq_proof.response),
+            },
+            schnorr_proof: SchnorrProofJson {
+                commitment: point_to_hex(&proof.schnorr_proof.commitment),
+                response: scalar_to_string(&proof.schnorr_proof.response),
+            },
+        }
+    }
+}
+
+impl<C: PoseidonCurve> TryFrom<&AnchoredProofJson> for AnchoredProof<C> {
+    type Error = AnchoredProofError;
+
+    fn try_from(json: &AnchoredProofJson) -> Result<Self, Self::Error> {
+        if json.version != PROOF_ENCODING_VERSION {
+            return Err(AnchoredProofError::InvalidEncoding(format!("unsupported version {}", json.version)));
+        }
+
+        let hashes = json
+            .merkle_path
+            .hashes
+            .iter()
+            .map(|h| hash_from_hex(h))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AnchoredProof {
+            key_id: key_id_from_hex(&json.key_id)?,
+            commitment: point_from_hex(&json.commitment)?,
+            modified_commitment: point_from_hex(&json.modified_commitment)?,
+            p_point: point_from_hex(&json.p_point)?,
+            leaf_hash: json.leaf_hash.as_deref().map(hash_from_hex).transpose()?,
+            leaf_index: json.merkle_path.leaf_index,
+            merkle_proof: MerkleProof::new(hashes),
+            dleq_proof: DLEQProof {
+                r_commitment_1: point_from_hex(&json.dleq_proof.r_commitment_1)?,
+                r_commitment_2: point_from_hex(&json.dleq_proof.r_commitment_2)?,
+                response: scalar_from_str(&json.dleq_proof.response)?,
+            },
+            schnorr_proof: SchnorrProof {
+                commitment: point_from_hex(&json.schnorr_proof.commitment)?,
+                response: scalar_from_str(&json.schnorr_proof.response)?,
+            },
+        })
+    }
+}
+
+impl<C: PoseidonCurve> From<&TreeAnchor<C>> for TreeAnchorJson {
+    fn from(tree_anchor: &TreeAnchor<C>) -> Self {
+        TreeAnchorJson {
+            point: point_to_hex(&tree_anchor.point),
+            proof: DleqProofJson {
+                r_commitment_1: point_to_hex(&tree_anchor.proof.r_commitment_1),
+                r_commitment_2: point_to_hex(&tree_anchor.proof.r_commitment_2),
+                response: scalar_to_string(&tree_anchor.proof.response),
+            },
+        }
+    }
+}
+
+impl<C: PoseidonCurve> TryFrom<&TreeAnchorJson> for TreeAnchor<C> {
+    type Error = AnchoredProofError;
+
+    fn try_from(json: &TreeAnchorJson) -> Result<Self, Self::Error> {
+        Ok(TreeAnchor {
+            point: point_from_hex(&json.point)?,
+            proof: DLEQProof {
+                r_commitment_1: point_from_hex(&json.proof.r_commitment_1)?,
+                r_commitment_2: point_from_hex(&json.proof.r_commitment_2)?,
+                response: scalar_from_str(&json.proof.response)?,
+            },
+        })
+    }
+}
+
+impl<C: PoseidonCurve> From<&VerificationContext<C>> for VerificationContextJson {
+    fn from(context: &VerificationContext<C>) -> Self {
+        VerificationContextJson {
+            generator_g: point_to_hex(&context.generator_g),
+            generator_h: point_to_hex(&context.generator_h),
+            generator_b: point_to_hex(&context.generator_b),
+            anchor: point_to_hex(&context.anchor),
+            tree_root: hex::encode(context.tree_root),
+            leaves_len: context.leaves_len,
+            context: hex::encode(&context.context),
+            leaf_encoding: context.leaf_encoding.id().to_string(),
+        }
+    }
+}
+
+impl<C: PoseidonCurve> TryFrom<&VerificationContextJson> for VerificationContext<C> {
+    type Error = AnchoredProofError;
+
+    fn try_from(json: &VerificationContextJson) -> Result<Self, Self::Error> {
+        Ok(VerificationContext {
+            generator_g: point_from_hex(&json.generator_g)?,
+            generator_h: point_from_hex(&json.generator_h)?,
+            generator_b: point_from_hex(&json.generator_b)?,
+            anchor: point_from_hex(&json.anchor)?,
+            tree_root: hash_from_hex(&json.tree_root)?,
+            leaves_len: json.leaves_len,
+            context: hex::decode(&json.context).map_err(|e| AnchoredProofError::InvalidEncoding(format!("context: {e}")))?,
+            leaf_encoding: leaf_encoding_from_id(&json.leaf_encoding)?,
+        })
+    }
+}
+
+impl From<&Generator> for GeneratorJson {
+    fn from(generator: &Generator) -> Self {
+        GeneratorJson { label: generator.label.clone(), point: point_to_hex(&generator.point) }
+    }
+}
+
+impl TryFrom<&GeneratorJson> for Generator {
+    type Error = AnchoredProofError;
+
+    fn try_from(json: &GeneratorJson) -> Result<Self, Self::Error> {
+        Ok(Generator { label: json.label.clone(), point: point_from_hex(&json.point)? })
+    }
+}
+
+impl From<&PublicParameters> for PublicParametersJson {
+    fn from(params: &PublicParameters) -> Self {
+        PublicParametersJson {
+            version: params.version,
+            curve_id: params.curve_id.clone(),
+            poseidon_id: params.poseidon_id.clone(),
+            dst: params.dst.clone(),
+            generator_g: GeneratorJson::from(&params.generator_g),
+            generator_h: GeneratorJson::from(&params.generator_h),
+            generator_b: GeneratorJson::from(&params.generator_b),
+        }
+    }
+}
+
+/// Decodes the parameters without checking their derivation; callers run
+/// `PublicParameters::verify_derivation` before using them
+impl TryFrom<&PublicParametersJson> for PublicParameters {
+    type Error = AnchoredProofError;
+
+    fn try_from(json: &PublicParametersJson) -> Result<Self, Self::Error> {
+        Ok(PublicParameters {
+            version: json.version,
+            curve_id: json.curve_id.clone(),
+            poseidon_id: json.poseidon_id.clone(),
+            dst: json.dst.clone(),
+            generator_g: Generator::try_from(&json.generator_g)?,
+            generator_h: Generator::try_from(&json.generator_h)?,
+            generator_b: Generator::try_from(&json.generator_b)?,
+        })
+    }
+}
+
+impl From<&EncryptedIssuerKey> for EncryptedIssuerKeyJson {
+    fn from(key: &EncryptedIssuerKey) -> Self {
+        EncryptedIssuerKeyJson {
+            version: key.version,
+            key_id: hex::encode(key.key_id),
+            kdf: KdfParamsJson { memory_kib: key.kdf.memory_kib, iterations: key.kdf.iterations },
+            salt: hex::encode(key.salt),
+            nonce: hex::encode(key.nonce),
+            ciphertext: hex::encode(&key.ciphertext),
+        }
+    }
+}
+
+impl TryFrom<&EncryptedIssuerKeyJson> for EncryptedIssuerKey {
+    type Error = AnchoredProofError;
+
+    fn try_from(json: &EncryptedIssuerKeyJson) -> Result<Self, Self::Error> {
+        Ok(EncryptedIssuerKey {
+            version: json.version,
+            key_id: bytes_from_hex(&json.key_id, "key id")?,
+            kdf: KdfParams { memory_kib: json.kdf.memory_kib, iterations: json.kdf.iterations },
+            salt: bytes_from_hex(&json.salt, "salt")?,
+            nonce: bytes_from_hex(&json.nonce, "nonce")?,
+            ciphertext: hex::decode(&json.ciphertext)
+                .map_err(|e| AnchoredProofError::InvalidEncoding(format!("ciphertext: {e}")))?,
+        })
+    }
+}
+
+pub fn proof_to_json<C: PoseidonCurve>(proof: &AnchoredProof<C>) -> String {
+    serde_json::to_string_pretty(&AnchoredProofJson::from(proof)).expect("proof JSON is always serializable")
+}
+
+pub fn proof_from_json<C: PoseidonCurve>(s: &str) -> Result<AnchoredProof<C>, AnchoredProofError> {
+    let json: AnchoredProofJson =
+        serde_json::from_str(s).map_err(|e| AnchoredProofError::InvalidEncoding(e.to_string()))?;
+    AnchoredProof::try_from(&json)
+}
+
+pub fn context_to_json<C: PoseidonCurve>(context: &VerificationContext<C>) -> String {
+    serde_json::to_string_pretty(&VerificationContextJson::from(context)).expect("context JSON is always serializable")
+}
+
+pub fn context_from_json<C: PoseidonCurve>(s: &str) -> Result<VerificationContext<C>, AnchoredProofError> {
+    let json: VerificationContextJson =
+        serde_json::from_str(s).map_err(|e| AnchoredProofError::InvalidEncoding(e.to_string()))?;
+    VerificationContext::try_from(&json)
+}
+
+pub fn params_to_json(params: &PublicParameters) -> String {
+    serde_json::to_string_pretty(&PublicParametersJson::from(params)).expect("parameter JSON is always serializable")
+}
+
+/// Decodes public parameters and checks that every generator matches its label
+pub fn params_from_json(s: &str) -> Result<PublicParameters, AnchoredProofError> {
+    let json: PublicParametersJson =
+        serde_json::from_str(s).map_err(|e| AnchoredProofError::InvalidEncoding(e.to_string()))?;
+    let params = PublicParameters::try_from(&json)?;
+    params.verify_derivation()?;
+    Ok(params)
+}
+
+pub fn issuer_key_to_json(key: &EncryptedIssuerKey) -> String {
+    serde_json::to_string_pretty(&EncryptedIssuerKeyJson::from(key)).expect("issuer key JSON is always serializable")
+}
+
+pub fn issuer_key_from_json(s: &str) -> Result<EncryptedIssuerKey, AnchoredProofError> {
+    let json: EncryptedIssuerKeyJson =
+        serde_json::from_str(s).map_err(|e| AnchoredProofError::InvalidEncoding(e.to_string()))?;
+    EncryptedIssuerKey::try_from(&json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fr, G1Affine, G1Projective};
+    use ark_ed_on_bn254::EdwardsProjective;
+    use crate::babyjubjub;
+    use crate::{setup::*, verify::verify_anchored_proof, IssuerKeypair};
+    use crate::prove::generate_anchored_proof;
+    use crate::test_utils::{Fixture, proof_and_context};
+    use crate::ProofInput;
+    use zeroize::Zeroizing;
+    use crate::tree_anchor::{tree_anchor_setup, verify_tree_anchor};
+
+    #[test]
+    fn test_proof_and_context_round_trip() {
+        let (proof, context) = proof_and_context();
+
+        let proof_json = proof_to_json(&proof);
+        let context_json = context_to_json(&context);
+
+        let decoded_proof: AnchoredProof = proof_from_json(&proof_json).unwrap();
+        let decoded_context = context_from_json(&context_json).unwrap();
+
+        assert_eq!(verify_anchored_proof(&decoded_proof, &decoded_context), Ok(()));
+        assert_eq!(proof_to_json(&decoded_proof), proof_json);
+        assert_eq!(context_to_json(&decoded_context), context_json);
+
+        // A missing `leaf_encoding` never selects the legacy encoding
+        let mut old_context: serde_json::Value = serde_json::from_str(&context_json).unwrap();
+        old_context.as_object_mut().unwrap().remove("leaf_encoding");
+        let old_context: VerificationContext = context_from_json(&old_context.to_string()).unwrap();
+        assert_eq!(old_context.leaf_encoding, LeafEncoding::Full);
+    }
+
+    // x-only leaves cannot tell P from -P, so a legacy tree over 1..=8 also
+    // holds the leaf for -3: C = -3G + bH passes both sub-proofs
+    #[test]
+    fn test_negated_witness_rejected_without_explicit_legacy_encoding() {
+        let mut fixture = Fixture::new(0);
+        let range = ValueRange::power_of_two(3).unwrap();
+        let legacy = LeafEncoding::LegacyXOnly;
+        fixture.tree = tree_setup_range_with_encoding(range, &fixture.g, &fixture.anchor, &fixture.secret, legacy).unwrap();
+        let negated = Zeroizing::new(-Fr::from(3u64));
+        let input = ProofInput { leaf_encoding: legacy, ..fixture.input(&negated) };
+        let proof = generate_anchored_proof(input).unwrap();
+
+        let mut context_json: serde_json::Value = serde_json::from_str(&context_to_json(&fixture.context())).unwrap();
+        context_json.as_object_mut().unwrap().remove("leaf_encoding");
+        let context = context_from_json(&context_json.to_string()).unwrap();
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_baby_jubjub_round_trip() {
+        let range = ValueRange::new(10, 20).unwrap();
+        let fixture = Fixture::<EdwardsProjective>::with_generators(babyjubjub::generator_setup(), range);
+        let (proof, context) = (fixture.prove(13), fixture.context());
+
+        let proof_json = proof_to_json(&proof);
+        let context_json = context_to_json(&context);
+        let value: serde_json::Value = serde_json::from_str(&proof_json).unwrap();
+        assert_eq!(value["commitment"].as_str().unwrap().len(), 64);
+
+        let decoded_proof: babyjubjub::AnchoredProof = proof_from_json(&proof_json).unwrap();
+        let decoded_context: babyjubjub::VerificationContext = context_from_json(&context_json).unwrap();
+        assert_eq!(verify_anchored_proof(&decoded_proof, &decoded_context), Ok(()));
+        assert_eq!(proof_to_json(&decoded_proof), proof_json);
+
+        // Baby Jubjub points do not decode as BN254 points
+        assert!(proof_from_json::<G1Projective>(&proof_json).is_err());
+    }
+
+    #[test]
+    fn test_params_round_trip() {
+        let params = PublicParameters::default();
+        let json = params_to_json(&params);
+        assert_eq!(params_from_json(&json), Ok(params.clone()));
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value["generator_g"]["label"].is_null());
+        assert_eq!(value["generator_h"]["label"], "H");
+
+        // A swapped point no longer matches its label
+        let mut tampered = PublicParametersJson::from(&params);
+        tampered.generator_h.point = tampered.generator_b.point.clone();
+        let tampered = serde_json::to_string(&tampered).unwrap();
+        assert!(matches!(params_from_json(&tampered), Err(AnchoredProofError::InvalidParameters(_))));
+    }
+
+    #[test]
+    fn test_issuer_key_round_trip() {
+        let (_, _, b) = generator_setup();
+        let keypair: IssuerKeypair = IssuerKeypair::generate(&b, &mut rand::rngs::OsRng);
+        let kdf = KdfParams { memory_kib: 64, iterations: 1 };
+        let envelope = keypair.export_with_kdf(b"passphrase", kdf, &mut rand::rngs::OsRng).unwrap();
+
+        let decoded = issuer_key_from_json(&issuer_key_to_json(&envelope)).unwrap();
+        assert_eq!(decoded, envelope);
+        let imported: IssuerKeypair = IssuerKeypair::import(&decoded, b"passphrase", &b).unwrap();
+        assert_eq!(imported.anchor(), keypair.anchor());
+
+        let mut short_salt = EncryptedIssuerKeyJson::from(&envelope);
+        short_salt.salt = "abcd".to_string();
+        assert!(matches!(EncryptedIssuerKey::try_from(&short_salt), Err(AnchoredProofError::InvalidEncoding(_))));
+    }
+
+    #[test]
+    fn test_tree_anchor_round_trip() {
+        let (g, _, b) = generator_setup();
+        let keypair: IssuerKeypair = IssuerKeypair::generate(&b, &mut rand::rngs::OsRng);
+        let tree_anchor: TreeAnchor = tree_anchor_setup(keypair.secret(), &g, &b, &mut rand::rngs::OsRng).unwrap();
+
+        let encoded = serde_json::to_string(&TreeAnchorJson::from(&tree_anchor)).unwrap();
+        let json: TreeAnchorJson = serde_json::from_str(&encoded).unwrap();
+        let decoded = TreeAnchor::<G1Projective>::try_from(&json).unwrap();
+        assert_eq!(decoded.point, tree_anchor.point);
+        assert_eq!(verify_tree_anchor(&decoded, &g, &b, keypair.anchor()), Ok(()));
+    }
+
+    #[test]
+    fn test_schema_field_formats() {
+        let (proof, _) = proof_and_context();
+        let value: serde_json::Value = serde_json::from_str(&proof_to_json(&proof)).unwrap();
+
+        assert_eq!(value["version"], PROOF_ENCODING_VERSION);
+        assert_eq!(value["key_id"].as_str().unwrap().len(), 64);
+        assert_eq!(value["commitment"].as_str().unwrap().len(), 64);
+        assert_eq!(value["merkle_path"]["leaf_index"], 3);
+        assert_eq!(value["merkle_path"]["hashes"].as_array().unwrap().len(), 3);
+        assert!(value["dleq_proof"]["response"].as_str().unwrap().bytes().all(|b| b.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_rejects_malformed_fields() {
+        let (proof, _) = proof_and_context();
+        let json = AnchoredProofJson::from(&proof);
+
+        let mut bad_point = json.clone();
+        bad_point.p_point = "zz".to_string();
+        assert!(matches!(AnchoredProof::<G1Projective>::try_from(&bad_point), Err(AnchoredProofError::InvalidEncoding(_))));
+
+        let mut identity = json.clone();
+        identity.p_point = point_to_hex(&G1Affine::zero());
+        assert_eq!(AnchoredProof::<G1Projective>::try_from(&identity).err(), Some(AnchoredProofError::PointAtInfinity));
+
+        let mut bad_scalar = json.clone();
+        bad_scalar.schnorr_proof.response = "0x12".to_string();
+        assert!(matches!(AnchoredProof::<G1Projective>::try_from(&bad_scalar), Err(AnchoredProofError::InvalidEncoding(_))));
+        bad_scalar.schnorr_proof.response = "-1".to_string();
+        assert!(matches!(AnchoredProof::<G1Projective>::try_from(&bad_scalar), Err(AnchoredProofError::InvalidEncoding(_))));
+        bad_scalar.schnorr_proof.response = "007".to_string();
+        assert!(matches!(AnchoredProof::<G1Projective>::try_from(&bad_scalar), Err(AnchoredProofError::InvalidEncoding(_))));
+
+        let mut short_hash = json.clone();
+        short_hash.leaf_hash = Some("abcd".to_string());
+        assert!(matches!(AnchoredProof::<G1Projective>::try_from(&short_hash), Err(AnchoredProofError::InvalidEncoding(_))));
+
+        let mut out_of_field = json.clone();
+        out_of_field.merkle_path.hashes[0] = "ff".repeat(32);
+        assert!(matches!(AnchoredProof::<G1Projective>::try_from(&out_of_field), Err(AnchoredProofError::InvalidEncoding(_))));
+
+        // Key ids are SHA-256 digests, not field elements
+        let mut high_key_id = json.clone();
+        high_key_id.key_id = "ff".repeat(32);
+        assert_eq!(AnchoredProof::<G1Projective>::try_from(&high_key_id).unwrap().key_id, [0xff; 32]);
+
+        let mut bad_version = json;
+        bad_version.version += 1;
+        assert!(matches!(AnchoredProof::<G1Projective>::try_from(&bad_version), Err(AnchoredProofError::InvalidEncoding(_))));
+    }
+}
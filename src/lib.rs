@@ -1,65 +1,138 @@
-use ark_bn254::{Fr, G1Affine};
+use ark_bn254::{Fr, G1Projective};
 use ark_ff::{BigInteger, PrimeField};
-use light_poseidon::{Poseidon, PoseidonBytesHasher};
-use rs_merkle::{Hasher, MerkleProof, MerkleTree};
+use rs_merkle::{Hasher, MerkleProof};
+use ark_ec::{AffineRepr, CurveGroup};
+use zeroize::Zeroizing;
 
 
 pub mod setup;
 pub mod prove;
 pub mod verify;
+pub mod nonce;
+pub mod error;
+pub mod serialize;
+pub mod json;
+pub mod tree;
+pub mod poseidon;
+pub mod babyjubjub;
+pub mod curve;
+pub mod hash_to_curve;
+pub mod params;
+pub mod issuer;
+pub mod transcript;
+pub mod tree_anchor;
+pub mod audit;
+#[cfg(test)]
+mod test_utils;
+
+pub use curve::PoseidonCurve;
+pub use error::AnchoredProofError;
+pub use issuer::{IssuerKeypair, KeyId};
+pub use params::PublicParameters;
+pub use tree::IndexedTree;
+pub use tree_anchor::TreeAnchor;
+
+use poseidon::{poseidon_hash, poseidon_hash_bytes_be};
 
 pub const LEAVES_POSEIDON_DOMAIN: u64 = 1;
+pub const PADDING_POSEIDON_DOMAIN: u64 = 2;
+pub const DLEQ_POSEIDON_DOMAIN: u64 = 3;
+pub const SCHNORR_POSEIDON_DOMAIN: u64 = 4;
+pub const TREE_ANCHOR_POSEIDON_DOMAIN: u64 = 5;
 
 #[derive(Clone)]
 pub struct PoseidonMerkleHasher;
 
-pub struct ProofInput<'a> {
-    pub secret: &'a Fr,
-    pub witness: &'a Fr,
-    pub blinding: &'a Fr,
-    pub generator_g: &'a G1Affine,
-    pub generator_h: &'a G1Affine,
-    pub generator_b: &'a G1Affine,
-    pub anchor: &'a G1Affine,
-    pub tree: &'a MerkleTree<PoseidonMerkleHasher>,
+/// How anchored leaves encode the anchor and P
+///
+/// Legacy roots are never assumed: a missing `leaf_encoding` in a tree file or
+/// context JSON means `Full`. Rebuild an old root with
+/// `tree_setup_range_with_encoding(.., LegacyXOnly)` and set `leaf_encoding` on
+/// both `ProofInput` and `VerificationContext` to prove and verify against it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LeafEncoding {
+    /// x-coordinate limbs and the parity of y (`PoseidonCurve::point_to_fields`)
+    #[default]
+    Full,
+    /// x-coordinate limbs only, as in trees built before y was bound. P and -P
+    /// share a leaf; kept so that previously published roots can be checked.
+    LegacyXOnly,
+}
+
+impl LeafEncoding {
+    pub fn id(&self) -> &'static str {
+        match self {
+            LeafEncoding::Full => "full",
+            LeafEncoding::LegacyXOnly => "legacy-x-only",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [LeafEncoding::Full, LeafEncoding::LegacyXOnly].into_iter().find(|encoding| encoding.id() == id)
+    }
+}
+
+/// Prover inputs over the curve `C`, BN254 G1 unless stated otherwise
+///
+/// The secret, witness and blinding are held in `Zeroizing` containers so they
+/// are wiped when their owner drops them.
+pub struct ProofInput<'a, C: CurveGroup = G1Projective> {
+    pub secret: &'a Zeroizing<C::ScalarField>,
+    pub witness: &'a Zeroizing<C::ScalarField>,
+    pub blinding: &'a Zeroizing<C::ScalarField>,
+    pub generator_g: &'a C::Affine,
+    pub generator_h: &'a C::Affine,
+    pub generator_b: &'a C::Affine,
+    pub anchor: &'a C::Affine,
+    pub tree: &'a IndexedTree,
+    /// Caller-supplied bytes bound into both challenges, e.g. a verifier nonce
+    pub context: &'a [u8],
+    /// Encoding `tree` was built with; `LegacyXOnly` only for old roots
+    pub leaf_encoding: LeafEncoding,
 }
 
-pub struct AnchoredProof {
-    pub commitment: G1Affine,
-    pub modified_commitment: G1Affine,
-    pub p_point: G1Affine,  // The point P = G*(secret*witness) used in leaf computation
-    pub leaf_hash: [u8; 32],
+pub struct AnchoredProof<C: CurveGroup = G1Projective> {
+    pub key_id: KeyId,  // Identifies the issuer anchor the proof was made under
+    pub commitment: C::Affine,
+    pub modified_commitment: C::Affine,
+    pub p_point: C::Affine,  // The point P = G*(secret*witness) used in leaf computation
+    pub leaf_hash: Option<[u8; 32]>,  // Redundant: verifiers recompute the leaf from the anchor and P
+    pub leaf_index: usize,  // Position of the leaf among the tree leaves
     pub merkle_proof: MerkleProof<PoseidonMerkleHasher>,
-    pub dleq_proof: DLEQProof,
-    pub schnorr_proof: SchnorrProof,
+    pub dleq_proof: DLEQProof<C>,
+    pub schnorr_proof: SchnorrProof<C>,
 }
 
-pub struct DLEQProof {
-    pub r_commitment_1: G1Affine,
-    pub r_commitment_2: G1Affine,
-    pub response: Fr,             
+pub struct DLEQProof<C: CurveGroup = G1Projective> {
+    pub r_commitment_1: C::Affine,
+    pub r_commitment_2: C::Affine,
+    pub response: C::ScalarField,
 }
 
-pub struct SchnorrProof {
-    pub commitment: G1Affine, 
-    pub response: Fr,      
+pub struct SchnorrProof<C: CurveGroup = G1Projective> {
+    pub commitment: C::Affine,
+    pub response: C::ScalarField,
 }
 
+// `Hasher::hash` cannot fail, so callers check untrusted hashes with
+// `is_canonical_hash` first (`IndexedTree::try_from_leaves`, the verifier)
+const NON_CANONICAL_NODE: &str = "Merkle nodes are checked to be below the field modulus";
+
 impl Hasher for PoseidonMerkleHasher {
     type Hash = [u8; 32];
     fn hash(data: &[u8]) -> Self::Hash {
         if data.len() == 64 {
             let (left, right) = data.split_at(32);
-            let mut poseidon = Poseidon::<Fr>::new_circom(2).unwrap();
-            poseidon.hash_bytes_be(&[left, right]).unwrap()
+            poseidon_hash_bytes_be(&[left, right]).expect(NON_CANONICAL_NODE)
         } 
         else {
-            let mut poseidon = Poseidon::<Fr>::new_circom(1).unwrap();
-            poseidon.hash_bytes_be(&[data]).unwrap()
+            poseidon_hash_bytes_be(&[data]).expect(NON_CANONICAL_NODE)
         }
     }
 }
 
+/// Splits a base field element into 128-bit little-endian limbs, each an Fr
+/// element: two for BN254 Fq, three for BLS12-381 Fq
 pub fn split_fq_to_fr<Fq, Fr>(fq_elem: &Fq) -> Vec<Fr>
 where
     Fq: PrimeField,
@@ -68,15 +141,63 @@ where
     let fq_bigint = fq_elem.into_bigint();
     let bytes = fq_bigint.to_bytes_le();
 
-    let (low_bytes, high_bytes) = bytes.split_at(16);
-    let low_fr = Fr::from_le_bytes_mod_order(low_bytes);
-    let high_fr = Fr::from_le_bytes_mod_order(high_bytes);
+    bytes.chunks(16).map(Fr::from_le_bytes_mod_order).collect()
+}
+
+/// Encodes the x-coordinate of `point` as Poseidon field elements
+pub fn point_x_limbs<A>(point: &A) -> Result<Vec<Fr>, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    A::Group::x_to_fields(point)
+}
+
+/// Leaf binding the anchor U to the point P:
+/// Poseidon(LEAVES_POSEIDON_DOMAIN, U encoding, P encoding) as 32 big-endian bytes
+pub fn anchored_leaf_hash<A>(anchor: &A, p: &A) -> Result<[u8; 32], AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    anchored_leaf_hash_with_encoding(anchor, p, LeafEncoding::Full)
+}
+
+pub fn anchored_leaf_hash_with_encoding<A>(anchor: &A, p: &A, encoding: LeafEncoding) -> Result<[u8; 32], AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    let encode = match encoding {
+        LeafEncoding::Full => A::Group::point_to_fields,
+        LeafEncoding::LegacyXOnly => A::Group::x_to_fields,
+    };
+    let mut inputs = vec![Fr::from(LEAVES_POSEIDON_DOMAIN)];
+    inputs.extend(encode(anchor)?);
+    inputs.extend(encode(p)?);
+
+    Ok(fr_to_bytes_be(&poseidon_hash(&inputs)?))
+}
+
+/// Whether `hash` is the big-endian encoding of an Fr element, as every
+/// Poseidon output is; anything else cannot be fed back into Poseidon
+pub fn is_canonical_hash(hash: &[u8; 32]) -> bool {
+    hash.as_slice() < Fr::MODULUS.to_bytes_be().as_slice()
+}
+
+/// Filler leaf for position `index`: Poseidon(PADDING_POSEIDON_DOMAIN, index).
+/// It lives outside the anchored leaf domain, so no witness can ever open it.
+pub fn padding_leaf_hash(index: usize) -> Result<[u8; 32], AnchoredProofError> {
+    let hash = poseidon_hash(&[Fr::from(PADDING_POSEIDON_DOMAIN), Fr::from(index as u64)])?;
+    Ok(fr_to_bytes_be(&hash))
+}
 
-    vec![low_fr, high_fr]
+fn fr_to_bytes_be(value: &Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    let v = value.into_bigint().to_bytes_be();
+    bytes[32 - v.len()..].copy_from_slice(&v);
+    bytes
 }
 
 #[cfg(test)]
-fn visualize_tree(tree: &MerkleTree<PoseidonMerkleHasher>) {
+fn visualize_tree(tree: &IndexedTree) {
 
     let leaves = tree.leaves().unwrap();
     let depth = tree.depth();
@@ -100,7 +221,7 @@ fn visualize_tree(tree: &MerkleTree<PoseidonMerkleHasher>) {
 
 #[cfg(test)]
 mod tests {
-    use ark_bn254::{Fq, Fr};
+    use ark_bn254::{Fq, Fr, G1Affine};
     use ark_ec::AffineRepr;
     use ark_ff::{BigInteger, PrimeField};
     use super::*;
@@ -127,7 +248,7 @@ mod tests {
         println!("Step 2: Merkle Tree Construction");
         
         let range = 8; 
-        let tree = tree_setup(range, &anchor, &secret);
+        let tree = tree_setup(&PublicParameters::default(), range, &anchor, &secret).unwrap();
 
         // ------------------------------------------------------------------
         // 3. WITNESS SELECTION
@@ -135,7 +256,7 @@ mod tests {
         println!("Step 3: Witness Selection");
 
         let witness_value = 2u64;
-        let witness = Fr::from(witness_value);
+        let witness = Zeroizing::new(Fr::from(witness_value));
 
         // ------------------------------------------------------------------
         // 4. PROOF GENERATION
@@ -151,9 +272,11 @@ mod tests {
             generator_b: &b,
             anchor: &anchor,
             tree: &tree,
+            context: &[],
+            leaf_encoding: LeafEncoding::Full,
         };
 
-        let proof = generate_anchored_proof(input);
+        let proof: AnchoredProof = generate_anchored_proof(input).unwrap();
 
         // ------------------------------------------------------------------
         // 5. ASSERTIONS & VALIDATION
@@ -165,7 +288,7 @@ mod tests {
         let valid_root = proof.merkle_proof.verify(
             tree.root().unwrap(),                 
             &[witness_index],                    
-            &[proof.leaf_hash],                  
+            &[proof.leaf_hash.unwrap()],
             tree.leaves_len()                    
         );
 
@@ -216,7 +339,7 @@ mod tests {
         let secret = secret_setup();
         let anchor = anchor_setup(&secret, &anchor_base);
         
-        let tree = tree_setup(range, &anchor, &secret);
+        let tree = tree_setup(&PublicParameters::default(), range, &anchor, &secret).unwrap();
 
         let expected_leaves = 1 << range;
         assert_eq!(tree.leaves_len(), expected_leaves);
@@ -230,6 +353,17 @@ mod tests {
         visualize_tree(&tree);
     }
 
+    #[test]
+    fn test_leaf_binds_y_parity() {
+        let (_, _, b) = generator_setup();
+        let anchor = anchor_setup(&secret_setup(), &b);
+        let p = G1Affine::generator();
+
+        assert_ne!(anchored_leaf_hash(&anchor, &p).unwrap(), anchored_leaf_hash(&anchor, &-p).unwrap());
+        let legacy = |p: &G1Affine| anchored_leaf_hash_with_encoding(&anchor, p, LeafEncoding::LegacyXOnly).unwrap();
+        assert_eq!(legacy(&p), legacy(&-p));
+    }
+
     #[test]
     #[ignore]
     fn distinct_and_not_default() {
@@ -0,0 +1,185 @@
+use ark_ff::{BigInteger, PrimeField};
+use hmac::{Hmac, Mac};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::transcript::Transcript;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const DLEQ_NONCE_LABEL: &[u8] = b"anchored-merkle-proof/dleq-nonce/v1";
+pub const SCHNORR_NONCE_LABEL: &[u8] = b"anchored-merkle-proof/schnorr-nonce/v1";
+
+/// Where the DLEQ and Schnorr nonces come from
+pub enum NonceMode<'a, R> {
+    /// Fresh nonce sampled from the RNG
+    Random(&'a mut R),
+    /// Nonce derived from the secret, the challenge transcript and optional extra randomness
+    Deterministic { extra: Option<&'a [u8]> },
+}
+
+impl<R: RngCore + CryptoRng> NonceMode<'_, R> {
+    /// `transcript` must hold everything the challenge hashes except the
+    /// commitment made with this nonce
+    pub(crate) fn nonce<F: PrimeField>(&mut self, secret: &F, label: &[u8], transcript: &Transcript) -> Zeroizing<F> {
+        match self {
+            NonceMode::Random(rng) => Zeroizing::new(F::rand(*rng)),
+            NonceMode::Deterministic { extra } => derive_nonce(secret, label, transcript, *extra),
+        }
+    }
+}
+
+/// RFC 6979-style nonce derivation (HMAC-DRBG over SHA-256)
+///
+/// The DRBG is keyed with the secret and seeded with a digest of the label,
+/// the encoded transcript (domain, statement, root, size and context) and the
+/// optional extra randomness. Two proofs whose challenges hash different inputs
+/// therefore never share a nonce, even if the extra input repeats.
+/// The DRBG state and the secret's encoding are wiped before returning.
+pub fn derive_nonce<F: PrimeField>(
+    secret: &F,
+    label: &[u8],
+    transcript: &Transcript,
+    extra: Option<&[u8]>,
+) -> Zeroizing<F> {
+    // int2octets(x): big-endian, fixed length
+    let secret_bytes = Zeroizing::new(secret.into_bigint().to_bytes_be());
+
+    let encoded = transcript.encode();
+    let mut digest = Sha256::new();
+    digest.update((label.len() as u64).to_be_bytes());
+    digest.update(label);
+    digest.update((encoded.len() as u64).to_be_bytes());
+    digest.update(&encoded);
+    match extra {
+        Some(bytes) => {
+            digest.update([1u8]);
+            digest.update((bytes.len() as u64).to_be_bytes());
+            digest.update(bytes);
+        }
+        None => digest.update([0u8]),
+    }
+    let message = digest.finalize();
+
+    // RFC 6979 section 3.2, steps b. to f.
+    let mut v = Zeroizing::new([0x01u8; 32]);
+    let mut k = Zeroizing::new([0x00u8; 32]);
+    *k = hmac(&k, &[&*v, &[0x00], &secret_bytes, &message]);
+    *v = hmac(&k, &[&*v]);
+    *k = hmac(&k, &[&*v, &[0x01], &secret_bytes, &message]);
+    *v = hmac(&k, &[&*v]);
+
+    // Step h. draws 64 bytes so the reduction mod r is statistically uniform
+    loop {
+        let mut t = Zeroizing::new([0u8; 64]);
+        *v = hmac(&k, &[&*v]);
+        t[..32].copy_from_slice(&*v);
+        *v = hmac(&k, &[&*v]);
+        t[32..].copy_from_slice(&*v);
+
+        let nonce = Zeroizing::new(F::from_be_bytes_mod_order(&*t));
+        if !nonce.is_zero() {
+            return nonce;
+        }
+        *k = hmac(&k, &[&*v, &[0x00]]);
+        *v = hmac(&k, &[&*v]);
+    }
+}
+
+fn hmac(key: &[u8; 32], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fr, G1Affine};
+    use ark_ec::{AffineRepr, CurveGroup};
+    use crate::transcript::Statement;
+    use crate::{DLEQ_POSEIDON_DOMAIN, SCHNORR_POSEIDON_DOMAIN};
+
+    // Fixed multiples of the canonical generator keep the vector independent of setup
+    fn transcript(domain: u64, context: &[u8]) -> Transcript {
+        let g = G1Affine::generator();
+        let mut transcript = Transcript::new(domain);
+        for k in 1..=3u64 {
+            transcript.absorb_point(&(g * Fr::from(k)).into_affine()).unwrap();
+        }
+        transcript.absorb_bytes(context);
+        transcript
+    }
+
+    #[test]
+    fn test_nonce_vector() {
+        let secret = Fr::from(42u64);
+        let nonce = derive_nonce(&secret, DLEQ_NONCE_LABEL, &transcript(DLEQ_POSEIDON_DOMAIN, b""), None);
+
+        assert_eq!(
+            hex::encode(nonce.into_bigint().to_bytes_be()),
+            "0654c710735cd7db95d225f44b2e202700196447017963dbb006e5417ad93bb6"
+        );
+        assert_eq!(nonce, derive_nonce(&secret, DLEQ_NONCE_LABEL, &transcript(DLEQ_POSEIDON_DOMAIN, b""), None));
+    }
+
+    #[test]
+    fn test_nonce_depends_on_the_whole_transcript() {
+        let secret = Fr::from(42u64);
+        let base = transcript(DLEQ_POSEIDON_DOMAIN, b"session-1");
+        let nonce = derive_nonce(&secret, DLEQ_NONCE_LABEL, &base, None);
+
+        let mut extended = transcript(DLEQ_POSEIDON_DOMAIN, b"session-1");
+        extended.absorb_u64(0);
+        assert_ne!(nonce, derive_nonce(&secret, DLEQ_NONCE_LABEL, &extended, None));
+        let other_context = transcript(DLEQ_POSEIDON_DOMAIN, b"session-2");
+        assert_ne!(nonce, derive_nonce(&secret, DLEQ_NONCE_LABEL, &other_context, None));
+        let other_domain = transcript(SCHNORR_POSEIDON_DOMAIN, b"session-1");
+        assert_ne!(nonce, derive_nonce(&secret, DLEQ_NONCE_LABEL, &other_domain, None));
+
+        assert_ne!(nonce, derive_nonce(&Fr::from(43u64), DLEQ_NONCE_LABEL, &base, None));
+        assert_ne!(nonce, derive_nonce(&secret, SCHNORR_NONCE_LABEL, &base, None));
+        assert_ne!(nonce, derive_nonce(&secret, DLEQ_NONCE_LABEL, &base, Some(&[])));
+        assert_ne!(
+            derive_nonce(&secret, DLEQ_NONCE_LABEL, &base, Some(b"boot-entropy-1")),
+            derive_nonce(&secret, DLEQ_NONCE_LABEL, &base, Some(b"boot-entropy-2"))
+        );
+    }
+
+    // Nonce over a full statement: G, H, B, the anchor, C, C' and P in that order
+    fn statement_nonce(points: &[G1Affine; 7], tree_root: &[u8; 32], leaves_len: usize) -> Zeroizing<Fr> {
+        let [generator_g, generator_h, generator_b, anchor, commitment, modified_commitment, p_point] = points;
+        let statement = Statement::<ark_bn254::G1Projective> {
+            generator_g,
+            generator_h,
+            generator_b,
+            anchor,
+            commitment,
+            modified_commitment,
+            p_point,
+            tree_root,
+            leaves_len,
+            context: b"session-1",
+        };
+        let transcript = statement.transcript(DLEQ_POSEIDON_DOMAIN).unwrap();
+        derive_nonce(&Fr::from(42u64), DLEQ_NONCE_LABEL, &transcript, None)
+    }
+
+    #[test]
+    fn test_nonce_changes_with_every_statement_element() {
+        let g = G1Affine::generator();
+        let points: [G1Affine; 7] = std::array::from_fn(|k| (g * Fr::from(k as u64 + 1)).into_affine());
+        let nonce = statement_nonce(&points, &[1; 32], 8);
+
+        for k in 0..points.len() {
+            let mut changed = points;
+            changed[k] = (g * Fr::from(100u64)).into_affine();
+            assert_ne!(nonce, statement_nonce(&changed, &[1; 32], 8), "point {k}");
+        }
+        assert_ne!(nonce, statement_nonce(&points, &[2; 32], 8));
+        assert_ne!(nonce, statement_nonce(&points, &[1; 32], 16));
+    }
+}
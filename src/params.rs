@@ -0,0 +1,165 @@
+//! Public parameters and their nothing-up-my-sleeve derivation
+//!
+//! Every generator records how it was obtained: either the curve's standard
+//! generator or `hash_to_curve(dst, label)`. A verifier that receives a
+//! parameter set runs `verify_derivation()` to recompute each point instead of
+//! trusting the encoded coordinates. The CLI writes these parameters in
+//! `setup` and rejects a `params.json` that fails the check.
+
+use ark_bn254::G1Affine;
+use ark_ec::AffineRepr;
+
+use crate::AnchoredProofError;
+use crate::hash_to_curve::{GENERATOR_B_MESSAGE, GENERATOR_DST, GENERATOR_H_MESSAGE, hash_to_g1};
+use crate::poseidon::POSEIDON_PARAMETERS_ID;
+
+/// Current version of the protocol and its parameter format
+pub const PROTOCOL_VERSION: u8 = 1;
+
+pub const BN254_G1_CURVE_ID: &str = "bn254-g1";
+
+/// A generator together with the label it was hashed from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Generator {
+    /// `None` for the curve's standard generator
+    pub label: Option<String>,
+    pub point: G1Affine,
+}
+
+impl Generator {
+    pub fn standard() -> Self {
+        Generator { label: None, point: G1Affine::generator() }
+    }
+
+    pub fn hashed(dst: &str, label: &str) -> Self {
+        Generator { label: Some(label.to_string()), point: hash_to_g1(dst.as_bytes(), label.as_bytes()) }
+    }
+
+    fn rederive(&self, dst: &str) -> Self {
+        match &self.label {
+            Some(label) => Generator::hashed(dst, label),
+            None => Generator::standard(),
+        }
+    }
+}
+
+/// Curve, generators, hash parameters and protocol version shared by the
+/// issuer, provers and verifiers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicParameters {
+    pub version: u8,
+    pub curve_id: String,
+    pub poseidon_id: String,
+    /// Hash-to-curve domain-separation tag, the seed of every hashed generator
+    pub dst: String,
+    pub generator_g: Generator,
+    pub generator_h: Generator,
+    pub generator_b: Generator,
+}
+
+impl Default for PublicParameters {
+    /// The standard G with H and B hashed from "H" and "B" under `GENERATOR_DST`
+    fn default() -> Self {
+        PublicParameters::derive(GENERATOR_DST, None, GENERATOR_H_MESSAGE, GENERATOR_B_MESSAGE)
+    }
+}
+
+impl PublicParameters {
+    /// Parameters whose H and B, and G unless `g_label` is `None`, are hashed
+    /// from their labels under `dst`
+    pub fn derive(dst: &str, g_label: Option<&str>, h_label: &str, b_label: &str) -> Self {
+        PublicParameters {
+            version: PROTOCOL_VERSION,
+            curve_id: BN254_G1_CURVE_ID.to_string(),
+            poseidon_id: POSEIDON_PARAMETERS_ID.to_string(),
+            dst: dst.to_string(),
+            generator_g: g_label.map_or_else(Generator::standard, |label| Generator::hashed(dst, label)),
+            generator_h: Generator::hashed(dst, h_label),
+            generator_b: Generator::hashed(dst, b_label),
+        }
+    }
+
+    /// (G, H, B)
+    pub fn generators(&self) -> (G1Affine, G1Affine, G1Affine) {
+        (self.generator_g.point, self.generator_h.point, self.generator_b.point)
+    }
+
+    /// Checks the version and identifiers, recomputes every generator from its
+    /// label and rejects generators that coincide
+    pub fn verify_derivation(&self) -> Result<(), AnchoredProofError> {
+        let invalid = |reason: String| Err(AnchoredProofError::InvalidParameters(reason));
+
+        if self.version != PROTOCOL_VERSION {
+            return invalid(format!("unsupported version {}", self.version));
+        }
+        if self.curve_id != BN254_G1_CURVE_ID {
+            return invalid(format!("unsupported curve {:?}", self.curve_id));
+        }
+        if self.poseidon_id != POSEIDON_PARAMETERS_ID {
+            return invalid(format!("unsupported Poseidon parameters {:?}", self.poseidon_id));
+        }
+
+        for (name, generator) in [("G", &self.generator_g), ("H", &self.generator_h), ("B", &self.generator_b)] {
+            if *generator != generator.rederive(&self.dst) {
+                return invalid(format!("generator {name} does not match its derivation"));
+            }
+        }
+
+        let (g, h, b) = self.generators();
+        if g == h || h == b || g == b {
+            return invalid("generators are not distinct".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::generator_setup;
+
+    #[test]
+    fn test_default_parameters_verify() {
+        let params = PublicParameters::default();
+        assert_eq!(params.verify_derivation(), Ok(()));
+        assert_eq!(params.generators(), generator_setup());
+    }
+
+    #[test]
+    fn test_hashed_g_verifies() {
+        let params = PublicParameters::derive(GENERATOR_DST, Some("G"), "H", "B");
+        assert_eq!(params.verify_derivation(), Ok(()));
+        assert_ne!(params.generator_g.point, G1Affine::generator());
+    }
+
+    #[test]
+    fn test_rejects_tampered_parameters() {
+        let honest = PublicParameters::default();
+        let rejected = |params: PublicParameters| {
+            matches!(params.verify_derivation(), Err(AnchoredProofError::InvalidParameters(_)))
+        };
+
+        // A point someone chose, e.g. with a known discrete log relative to G
+        let mut params = honest.clone();
+        params.generator_h.point = (G1Affine::generator() * ark_bn254::Fr::from(7u64)).into();
+        assert!(rejected(params));
+
+        let mut params = honest.clone();
+        params.generator_b.label = Some("B2".to_string());
+        assert!(rejected(params));
+
+        let mut params = honest.clone();
+        params.dst = "OTHER-DST".to_string();
+        assert!(rejected(params));
+
+        let mut params = honest.clone();
+        params.version += 1;
+        assert!(rejected(params));
+
+        let mut params = honest.clone();
+        params.poseidon_id = "poseidon-other".to_string();
+        assert!(rejected(params));
+
+        assert!(rejected(PublicParameters::derive(GENERATOR_DST, None, "H", "H")));
+    }
+}
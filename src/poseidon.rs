@@ -0,0 +1,66 @@
+//! Poseidon hashers cached per arity
+//!
+//! `Poseidon::new_circom` rebuilds the round constants and MDS matrix on every
+//! call. Each thread instead keeps one hasher per input count and reuses it for
+//! leaves, Merkle nodes and Fiat-Shamir challenges alike.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonBytesHasher, PoseidonError, PoseidonHasher};
+
+/// Identifies the Poseidon instance: circomlib's parameters over BN254 Fr
+/// (x^5 S-box, 8 full rounds), as generated by `Poseidon::new_circom`
+pub const POSEIDON_PARAMETERS_ID: &str = "poseidon-circom-bn254-x5";
+
+thread_local! {
+    static HASHERS: RefCell<HashMap<usize, Poseidon<Fr>>> = RefCell::new(HashMap::new());
+}
+
+fn with_hasher<T>(
+    arity: usize,
+    f: impl FnOnce(&mut Poseidon<Fr>) -> Result<T, PoseidonError>,
+) -> Result<T, PoseidonError> {
+    HASHERS.with(|hashers| {
+        let mut hashers = hashers.borrow_mut();
+        let hasher = match hashers.entry(arity) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(Poseidon::<Fr>::new_circom(arity)?),
+        };
+        f(hasher)
+    })
+}
+
+/// Circom-compatible Poseidon over `inputs`
+pub fn poseidon_hash(inputs: &[Fr]) -> Result<Fr, PoseidonError> {
+    with_hasher(inputs.len(), |hasher| hasher.hash(inputs))
+}
+
+/// Circom-compatible Poseidon over big-endian field element encodings
+pub fn poseidon_hash_bytes_be(inputs: &[&[u8]]) -> Result<[u8; 32], PoseidonError> {
+    with_hasher(inputs.len(), |hasher| hasher.hash_bytes_be(inputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_hashers_match_fresh_ones() {
+        for arity in 1..=8 {
+            let inputs: Vec<Fr> = (0..arity as u64).map(|i| Fr::from(i + 1)).collect();
+            let expected = Poseidon::<Fr>::new_circom(arity).unwrap().hash(&inputs).unwrap();
+
+            assert_eq!(poseidon_hash(&inputs).unwrap(), expected);
+            // A second call reuses the cached hasher and must not carry state over
+            assert_eq!(poseidon_hash(&inputs).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_wrong_arity_is_an_error_not_a_panic() {
+        assert!(poseidon_hash(&[]).is_err());
+        assert!(poseidon_hash(&[Fr::from(1u64); 16]).is_err());
+    }
+}
@@ -1,145 +1,245 @@
-use ark_bn254::{Fr, G1Affine, G1Projective, g1};
-use ark_ec::{AffineRepr, CurveGroup, short_weierstrass::Affine};
-use ark_ff::{BigInteger, PrimeField, UniformRand};
-use ark_std::test_rng;
-use light_poseidon::{Poseidon, PoseidonHasher};
-use rs_merkle::MerkleProof;
+//! Generation of anchored proofs
+//!
+//! The prover wipes its nonces, the s*w and s*b temporaries and the RFC 6979
+//! DRBG state once a proof is built; `ProofInput` takes the secret scalars in
+//! `Zeroizing` containers so the caller's copies are wiped too.
+
+use rand::{CryptoRng, RngCore, rngs::OsRng};
+use zeroize::Zeroizing;
+
+use crate::issuer::key_id;
+use crate::nonce::{DLEQ_NONCE_LABEL, NonceMode, SCHNORR_NONCE_LABEL};
+use crate::transcript::Statement;
+use crate::{
+    AnchoredProof, AnchoredProofError, DLEQ_POSEIDON_DOMAIN, DLEQProof, PoseidonCurve, ProofInput,
+    SCHNORR_POSEIDON_DOMAIN, SchnorrProof, anchored_leaf_hash_with_encoding,
+};
+
+/// Generates an anchored proof with nonces drawn from OS entropy
+pub fn generate_anchored_proof<C: PoseidonCurve>(input: ProofInput<C>) -> Result<AnchoredProof<C>, AnchoredProofError> {
+    generate_anchored_proof_with_rng(input, &mut OsRng)
+}
 
-use crate::{AnchoredProof, DLEQProof, LEAVES_POSEIDON_DOMAIN, PoseidonMerkleHasher, ProofInput, SchnorrProof, split_fq_to_fr};
+pub fn generate_anchored_proof_with_rng<C: PoseidonCurve, R: RngCore + CryptoRng>(
+    input: ProofInput<C>,
+    rng: &mut R,
+) -> Result<AnchoredProof<C>, AnchoredProofError> {
+    generate_anchored_proof_with_nonces(input, NonceMode::Random(rng))
+}
 
-pub fn generate_anchored_proof(input: ProofInput) -> AnchoredProof {
+/// Generates an anchored proof whose nonces are derived from the secret and the
+/// public statement (RFC 6979-style), optionally hedged with extra randomness
+pub fn generate_anchored_proof_deterministic<C: PoseidonCurve>(
+    input: ProofInput<C>,
+    extra: Option<&[u8]>,
+) -> Result<AnchoredProof<C>, AnchoredProofError> {
+    let nonces: NonceMode<OsRng> = NonceMode::Deterministic { extra };
+    generate_anchored_proof_with_nonces(input, nonces)
+}
+
+fn generate_anchored_proof_with_nonces<C: PoseidonCurve, R: RngCore + CryptoRng>(
+    input: ProofInput<C>,
+    mut nonces: NonceMode<R>,
+) -> Result<AnchoredProof<C>, AnchoredProofError> {
     // 1. Reconstruct Commitments
-    let commitment = (*input.generator_g) * input.witness + (*input.generator_h) * input.blinding;
-    let modified_commitment = commitment.clone() * input.secret;
+    let commitment = (*input.generator_g) * **input.witness + (*input.generator_h) * **input.blinding;
+    let modified_commitment = commitment * **input.secret;
 
     // 2. Calculate P (The Anchor link)
-    let scalar = input.secret * input.witness;
-    let p: Affine<g1::Config> = ((*input.generator_g) * scalar).into();
+    let scalar = Zeroizing::new(**input.secret * **input.witness);
+    let p = ((*input.generator_g) * *scalar).into_affine();
 
     // 3. Merkle Leaf Generation
-    let anchor_x_limbs = split_fq_to_fr(&input.anchor.x().unwrap());
-    let p_x_limbs = split_fq_to_fr(&p.x().unwrap());
-    
-    let mut poseidon = Poseidon::<Fr>::new_circom(5).unwrap();
-    let hash = poseidon.hash(&[
-        Fr::from(LEAVES_POSEIDON_DOMAIN),
-        anchor_x_limbs[0], anchor_x_limbs[1], 
-        p_x_limbs[0], p_x_limbs[1]
-    ]).unwrap();
-
-    let mut bytes_hash = [0u8; 32];
-    let v = hash.into_bigint().to_bytes_be();
-    bytes_hash[32 - v.len()..].copy_from_slice(&v);
-    
-    // 4. Find Path (Safe Version)
-    let mut merkle_path: Option<MerkleProof<PoseidonMerkleHasher>> = None; 
-    for leave_index in 0..input.tree.leaves_len() {
-        if input.tree.leaves().unwrap()[leave_index] == bytes_hash {
-            merkle_path = Some(input.tree.proof(&[leave_index]));
-            break;
-        }
-    }
-    
-    let merkle_proof = merkle_path.expect("Leaf not found in tree! Inputs do not match any known leaf.");
-
-    let public_blinding = modified_commitment - p;
+    let bytes_hash = anchored_leaf_hash_with_encoding(input.anchor, &p, input.leaf_encoding)?;
+
+    // 4. Find Path
+    let leaf_index = input.tree.index_of_leaf(&bytes_hash).ok_or(AnchoredProofError::WitnessOutOfRange)?;
+    let merkle_proof = input.tree.proof(leaf_index).ok_or(AnchoredProofError::WitnessOutOfRange)?;
+
+    let public_blinding = (modified_commitment - p).into_affine();
+    let commitment = commitment.into_affine();
+    let modified_commitment = modified_commitment.into_affine();
+    let tree_root = input.tree.root().ok_or(AnchoredProofError::WitnessOutOfRange)?;
+
+    // 5. Fiat–Shamir statement shared by both sub-proofs
+    let statement = Statement::<C> {
+        generator_g: input.generator_g,
+        generator_h: input.generator_h,
+        generator_b: input.generator_b,
+        anchor: input.anchor,
+        commitment: &commitment,
+        modified_commitment: &modified_commitment,
+        p_point: &p,
+        tree_root: &tree_root,
+        leaves_len: input.tree.leaves_len(),
+        context: input.context,
+    };
     
     // 6. Generate Proofs
     
     // DLEQ: Proves Anchor and C' share the same secret 's' relative to bases B and C
     // Note: Ensure input.generator_b is truly the base of input.anchor
-    let dleq_proof = generate_dleq_proof(
-        &input.secret,
-        input.generator_b,          // Base for Anchor
-        &commitment.into_affine(),  // Base for Modified Commitment
-        input.anchor,               // Anchor
-        &modified_commitment.into_affine() // Modified Commitment
-    );
-
-    let composite_secret = input.secret * input.blinding;
+    let dleq_proof = generate_dleq_proof::<C, R>(
+        input.secret,
+        &statement,
+        &mut nonces,
+    )?;
+
+    let composite_secret = Zeroizing::new(**input.secret * **input.blinding);
     
-    let schnorr_proof = generate_schnorr_proof(
+    let schnorr_proof = generate_schnorr_proof::<C, R>(
         &composite_secret,    
-        input.generator_h,    
-        &public_blinding      
-    );
-
-    AnchoredProof { 
-        commitment: commitment.into(), 
-        modified_commitment: modified_commitment.into(),
-        p_point: p.into(),
-        leaf_hash: bytes_hash, 
+        &statement,
+        &public_blinding,
+        &mut nonces,
+    )?;
+
+    Ok(AnchoredProof { 
+        key_id: key_id(input.anchor),
+        commitment, 
+        modified_commitment,
+        p_point: p,
+        leaf_hash: Some(bytes_hash), 
+        leaf_index,
         merkle_proof, 
         dleq_proof, 
         schnorr_proof 
-    }
+    })
 } 
 
-fn generate_schnorr_proof(
-    secret: &Fr, 
-    generator: &G1Affine,
-    public: &G1Projective
-) -> SchnorrProof {
-    let mut rng = test_rng();
-
-    let r_scalar = Fr::rand(&mut rng);
+/// Proves knowledge of t with R_H = H*t
+fn generate_schnorr_proof<C: PoseidonCurve, R: RngCore + CryptoRng>(
+    secret: &C::ScalarField, 
+    statement: &Statement<C>,
+    public: &C::Affine,     // R_H
+    nonces: &mut NonceMode<R>,
+) -> Result<SchnorrProof<C>, AnchoredProofError> {
+    let generator = statement.generator_h;
+    let mut transcript = statement.transcript(SCHNORR_POSEIDON_DOMAIN)?;
+    transcript.absorb_point(public)?;
+    let r_scalar = nonces.nonce(secret, SCHNORR_NONCE_LABEL, &transcript);
     
-    let r_point = (*generator) * r_scalar;
+    let r_point = (*generator) * *r_scalar;
     let r_affine = r_point.into_affine();
-    let public_affine = (*public).into_affine();
-
-    let pk_limbs = split_fq_to_fr(&public_affine.x().unwrap());
-    let r_limbs = split_fq_to_fr(&r_affine.x().unwrap());
 
-    let mut poseidon = Poseidon::<Fr>::new_circom(4).unwrap();
-    let challenge = poseidon.hash(&[
-        pk_limbs[0], pk_limbs[1],
-        r_limbs[0], r_limbs[1]
-    ]).unwrap();
+    // H(statement, R_H, r)
+    let challenge = transcript.absorb_point(&r_affine)?.challenge::<C>()?;
 
-    let response = r_scalar + (challenge * secret);
+    let response = *r_scalar + (challenge * secret);
 
-    SchnorrProof {
+    Ok(SchnorrProof {
         commitment: r_affine,
         response,
-    }
+    })
+}
+
+/// Proves log_B(U) = log_C(C')
+fn generate_dleq_proof<C: PoseidonCurve, R: RngCore + CryptoRng>(
+    secret: &C::ScalarField,
+    statement: &Statement<C>,
+    nonces: &mut NonceMode<R>,
+) -> Result<DLEQProof<C>, AnchoredProofError> {
+    let (generator1, generator2) = (statement.generator_b, statement.commitment);
+    let mut transcript = statement.transcript(DLEQ_POSEIDON_DOMAIN)?;
+    let r = nonces.nonce(secret, DLEQ_NONCE_LABEL, &transcript);
+
+    let r1_affine = (*generator1 * *r).into_affine();
+    let r2_affine = (*generator2 * *r).into_affine();
+
+    // H(statement, R1, R2)
+    let challenge = transcript.absorb_point(&r1_affine)?.absorb_point(&r2_affine)?.challenge::<C>()?;
+
+    let response = *r + (challenge * secret);
+
+    Ok(DLEQProof {
+        r_commitment_1: r1_affine,
+        r_commitment_2: r2_affine,
+        response,
+    })
 }
 
-fn generate_dleq_proof(
-    secret: &Fr,
-    generator1: &G1Affine, // B
-    generator2: &G1Affine, // C
-    public1: &G1Affine,    // U
-    public2: &G1Affine     // C'
-) -> DLEQProof {
-    let mut rng = test_rng();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use crate::setup::*;
+    use crate::test_utils::Fixture;
+
+    // Secrets drawn from fixed seeds, so that deterministic proofs can repeat
+    fn seeded_fixture() -> Fixture {
+        let secret = secret_setup_with_rng(&mut seeded_rng(7));
+        let blinding = secret_setup_with_rng(&mut seeded_rng(8));
+        Fixture::with_secrets(generator_setup(), ValueRange::power_of_two(2).unwrap(), secret, blinding)
+    }
 
-    let r = Fr::rand(&mut rng);
+    #[test]
+    fn test_nonces_are_fresh_per_proof() {
+        let fixture = Fixture::new(2);
+        assert_ne!(fixture.secret, fixture.blinding, "OS entropy must not repeat secrets");
 
-    let r1_affine = (*generator1 * r).into_affine();
-    let r2_affine = (*generator2 * r).into_affine();
+        let first = fixture.prove(3);
+        let second = fixture.prove(3);
 
-    let u_limbs = split_fq_to_fr(&public1.x().unwrap());
-    let c_modified_limbs = split_fq_to_fr(&public2.x().unwrap());
+        assert_ne!(first.dleq_proof.r_commitment_1, second.dleq_proof.r_commitment_1);
+        assert_ne!(first.schnorr_proof.commitment, second.schnorr_proof.commitment);
+    }
 
-    let r1_limbs = split_fq_to_fr(&r1_affine.x().unwrap());
-    let r2_limbs = split_fq_to_fr(&r2_affine.x().unwrap());
+    #[test]
+    fn test_seeded_rng_is_reproducible() {
+        assert_eq!(secret_setup_with_rng(&mut seeded_rng(7)), secret_setup_with_rng(&mut seeded_rng(7)));
 
-    // Total inputs: 4 points * 2 limbs/point = 8 inputs
-    let mut poseidon = Poseidon::<Fr>::new_circom(8).unwrap();
+        let fixture = seeded_fixture();
+        let witness = Zeroizing::new(Fr::from(1u64));
 
-    let challenge = poseidon.hash(&[
-        u_limbs[0], u_limbs[1],   // U
-        c_modified_limbs[0], c_modified_limbs[1],   // C'
-        r1_limbs[0], r1_limbs[1], // R1
-        r2_limbs[0], r2_limbs[1]  // R2
-    ]).unwrap();
+        let prove = || -> AnchoredProof { generate_anchored_proof_with_rng(fixture.input(&witness), &mut seeded_rng(9)).unwrap() };
+        let first = prove();
+        let second = prove();
 
-    let response = r + (challenge * secret);
+        assert_eq!(first.dleq_proof.response, second.dleq_proof.response);
+        assert_eq!(first.schnorr_proof.response, second.schnorr_proof.response);
+    }
 
-    DLEQProof {
-        r_commitment_1: r1_affine,
-        r_commitment_2: r2_affine,
-        response,
+    #[test]
+    fn test_deterministic_proofs_repeat_per_statement() {
+        let fixture = seeded_fixture();
+        let one = Zeroizing::new(Fr::from(1u64));
+        let two = Zeroizing::new(Fr::from(2u64));
+
+        let first: AnchoredProof = generate_anchored_proof_deterministic(fixture.input(&one), None).unwrap();
+        let again: AnchoredProof = generate_anchored_proof_deterministic(fixture.input(&one), None).unwrap();
+        assert_eq!(first.dleq_proof.r_commitment_1, again.dleq_proof.r_commitment_1);
+        assert_eq!(first.dleq_proof.response, again.dleq_proof.response);
+        assert_eq!(first.schnorr_proof.commitment, again.schnorr_proof.commitment);
+        assert_eq!(first.schnorr_proof.response, again.schnorr_proof.response);
+
+        let other: AnchoredProof = generate_anchored_proof_deterministic(fixture.input(&two), None).unwrap();
+        assert_ne!(first.dleq_proof.r_commitment_1, other.dleq_proof.r_commitment_1);
+        // R_H = H*(s*b) does not depend on the witness, but the Schnorr challenge
+        // hashes C, C' and P as well, so its nonce must change with them
+        assert_ne!(first.schnorr_proof.commitment, other.schnorr_proof.commitment);
+
+        let hedged: AnchoredProof =
+            generate_anchored_proof_deterministic(fixture.input(&one), Some(b"boot-entropy")).unwrap();
+        assert_ne!(first.dleq_proof.r_commitment_1, hedged.dleq_proof.r_commitment_1);
+    }
+
+    #[test]
+    fn test_deterministic_nonces_follow_the_context() {
+        let fixture = seeded_fixture();
+        let witness = Zeroizing::new(Fr::from(1u64));
+        let input = |context| ProofInput { context, ..fixture.input(&witness) };
+
+        // Same statement except for the context: reusing either nonce under
+        // the two different challenges would reveal the secret
+        let first: AnchoredProof = generate_anchored_proof_deterministic(input(b"verifier-nonce-1"), None).unwrap();
+        let second: AnchoredProof = generate_anchored_proof_deterministic(input(b"verifier-nonce-2"), None).unwrap();
+        assert_ne!(first.dleq_proof.r_commitment_1, second.dleq_proof.r_commitment_1);
+        assert_ne!(first.schnorr_proof.commitment, second.schnorr_proof.commitment);
+    }
+
+    #[test]
+    fn test_witness_outside_tree_is_rejected() {
+        let fixture = Fixture::new(2);
+        assert!(matches!(fixture.try_prove(5), Err(AnchoredProofError::WitnessOutOfRange)));
     }
-}
\ No newline at end of file
+}
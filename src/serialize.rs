@@ -0,0 +1,362 @@
+//! Canonical binary encoding of proofs
+//!
+//! `AnchoredProof` is written as a version byte followed by its fields in
+//! declaration order; points follow the arkworks compressed or uncompressed
+//! encoding, the optional leaf hash is a presence byte followed by the hash and
+//! the Merkle path is a length-prefixed list of at most `MAX_PATH_LEN` 32-byte
+//! hashes. Standalone `DLEQProof` and `SchnorrProof` encodings start with
+//! their own version byte; the copies nested inside `AnchoredProof` do not.
+//! Validated deserialization rejects points that are off the curve, outside
+//! the prime-order subgroup or equal to the identity.
+
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate, Write};
+use rs_merkle::MerkleProof;
+
+use crate::{AnchoredProof, DLEQProof, SchnorrProof, is_canonical_hash};
+
+/// Current version of the `AnchoredProof` encoding
+pub const PROOF_ENCODING_VERSION: u8 = 3;
+
+/// Current version of the standalone `DLEQProof` and `SchnorrProof` encodings
+pub const SUB_PROOF_ENCODING_VERSION: u8 = 1;
+
+/// Longest Merkle path accepted: one hash per level of a tree with at most
+/// `usize::MAX` leaves
+pub const MAX_PATH_LEN: u64 = usize::BITS as u64;
+
+fn check_point<A: AffineRepr>(point: &A) -> Result<(), SerializationError> {
+    point.check()?;
+    if point.is_zero() {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok(())
+}
+
+// Sub-proof fields without a version byte, shared by the standalone encodings
+// and the copies nested inside `AnchoredProof`, which carries its own version
+
+fn serialize_dleq<C: CurveGroup, W: Write>(proof: &DLEQProof<C>, mut writer: W, compress: Compress) -> Result<(), SerializationError> {
+    proof.r_commitment_1.serialize_with_mode(&mut writer, compress)?;
+    proof.r_commitment_2.serialize_with_mode(&mut writer, compress)?;
+    proof.response.serialize_with_mode(&mut writer, compress)
+}
+
+fn dleq_size<C: CurveGroup>(proof: &DLEQProof<C>, compress: Compress) -> usize {
+    proof.r_commitment_1.serialized_size(compress)
+        + proof.r_commitment_2.serialized_size(compress)
+        + proof.response.serialized_size(compress)
+}
+
+fn deserialize_dleq<C: CurveGroup, R: Read>(mut reader: R, compress: Compress, validate: Validate) -> Result<DLEQProof<C>, SerializationError> {
+    let proof = DLEQProof {
+        r_commitment_1: C::Affine::deserialize_with_mode(&mut reader, compress, validate)?,
+        r_commitment_2: C::Affine::deserialize_with_mode(&mut reader, compress, validate)?,
+        response: C::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
+    };
+    if validate == Validate::Yes {
+        proof.check()?;
+    }
+    Ok(proof)
+}
+
+fn serialize_schnorr<C: CurveGroup, W: Write>(proof: &SchnorrProof<C>, mut writer: W, compress: Compress) -> Result<(), SerializationError> {
+    proof.commitment.serialize_with_mode(&mut writer, compress)?;
+    proof.response.serialize_with_mode(&mut writer, compress)
+}
+
+fn schnorr_size<C: CurveGroup>(proof: &SchnorrProof<C>, compress: Compress) -> usize {
+    proof.commitment.serialized_size(compress) + proof.response.serialized_size(compress)
+}
+
+fn deserialize_schnorr<C: CurveGroup, R: Read>(mut reader: R, compress: Compress, validate: Validate) -> Result<SchnorrProof<C>, SerializationError> {
+    let proof = SchnorrProof {
+        commitment: C::Affine::deserialize_with_mode(&mut reader, compress, validate)?,
+        response: C::ScalarField::deserialize_with_mode(&mut reader, compress, validate)?,
+    };
+    if validate == Validate::Yes {
+        proof.check()?;
+    }
+    Ok(proof)
+}
+
+fn read_version<R: Read>(reader: R, expected: u8, compress: Compress, validate: Validate) -> Result<(), SerializationError> {
+    let version = u8::deserialize_with_mode(reader, compress, validate)?;
+    if version != expected {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok(())
+}
+
+impl<C: CurveGroup> CanonicalSerialize for DLEQProof<C> {
+    fn serialize_with_mode<W: Write>(&self, mut writer: W, compress: Compress) -> Result<(), SerializationError> {
+        SUB_PROOF_ENCODING_VERSION.serialize_with_mode(&mut writer, compress)?;
+        serialize_dleq(self, writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        SUB_PROOF_ENCODING_VERSION.serialized_size(compress) + dleq_size(self, compress)
+    }
+}
+
+impl<C: CurveGroup> Valid for DLEQProof<C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        check_point(&self.r_commitment_1)?;
+        check_point(&self.r_commitment_2)
+    }
+}
+
+impl<C: CurveGroup> CanonicalDeserialize for DLEQProof<C> {
+    fn deserialize_with_mode<R: Read>(mut reader: R, compress: Compress, validate: Validate) -> Result<Self, SerializationError> {
+        read_version(&mut reader, SUB_PROOF_ENCODING_VERSION, compress, validate)?;
+        deserialize_dleq(reader, compress, validate)
+    }
+}
+
+impl<C: CurveGroup> CanonicalSerialize for SchnorrProof<C> {
+    fn serialize_with_mode<W: Write>(&self, mut writer: W, compress: Compress) -> Result<(), SerializationError> {
+        SUB_PROOF_ENCODING_VERSION.serialize_with_mode(&mut writer, compress)?;
+        serialize_schnorr(self, writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        SUB_PROOF_ENCODING_VERSION.serialized_size(compress) + schnorr_size(self, compress)
+    }
+}
+
+impl<C: CurveGroup> Valid for SchnorrProof<C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        check_point(&self.commitment)
+    }
+}
+
+impl<C: CurveGroup> CanonicalDeserialize for SchnorrProof<C> {
+    fn deserialize_with_mode<R: Read>(mut reader: R, compress: Compress, validate: Validate) -> Result<Self, SerializationError> {
+        read_version(&mut reader, SUB_PROOF_ENCODING_VERSION, compress, validate)?;
+        deserialize_schnorr(reader, compress, validate)
+    }
+}
+
+impl<C: CurveGroup> CanonicalSerialize for AnchoredProof<C> {
+    fn serialize_with_mode<W: Write>(&self, mut writer: W, compress: Compress) -> Result<(), SerializationError> {
+        PROOF_ENCODING_VERSION.serialize_with_mode(&mut writer, compress)?;
+        self.key_id.serialize_with_mode(&mut writer, compress)?;
+        self.commitment.serialize_with_mode(&mut writer, compress)?;
+        self.modified_commitment.serialize_with_mode(&mut writer, compress)?;
+        self.p_point.serialize_with_mode(&mut writer, compress)?;
+        self.leaf_hash.serialize_with_mode(&mut writer, compress)?;
+        (self.leaf_index as u64).serialize_with_mode(&mut writer, compress)?;
+        self.merkle_proof.proof_hashes().to_vec().serialize_with_mode(&mut writer, compress)?;
+        serialize_dleq(&self.dleq_proof, &mut writer, compress)?;
+        serialize_schnorr(&self.schnorr_proof, &mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        PROOF_ENCODING_VERSION.serialized_size(compress)
+            + self.key_id.serialized_size(compress)
+            + self.commitment.serialized_size(compress)
+            + self.modified_commitment.serialized_size(compress)
+            + self.p_point.serialized_size(compress)
+            + self.leaf_hash.serialized_size(compress)
+            + (self.leaf_index as u64).serialized_size(compress)
+            + self.merkle_proof.proof_hashes().to_vec().serialized_size(compress)
+            + dleq_size(&self.dleq_proof, compress)
+            + schnorr_size(&self.schnorr_proof, compress)
+    }
+}
+
+impl<C: CurveGroup> Valid for AnchoredProof<C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        check_point(&self.commitment)?;
+        check_point(&self.modified_commitment)?;
+        check_point(&self.p_point)?;
+        self.dleq_proof.check()?;
+        self.schnorr_proof.check()
+    }
+}
+
+impl<C: CurveGroup> CanonicalDeserialize for AnchoredProof<C> {
+    fn deserialize_with_mode<R: Read>(mut reader: R, compress: Compress, validate: Validate) -> Result<Self, SerializationError> {
+        read_version(&mut reader, PROOF_ENCODING_VERSION, compress, validate)?;
+
+        let key_id = <[u8; 32]>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let commitment = C::Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        let modified_commitment = C::Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        let p_point = C::Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        let leaf_hash = Option::<[u8; 32]>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let leaf_index = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        // The length prefix is untrusted, so it bounds the loop rather than an allocation
+        let path_len = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        if path_len > MAX_PATH_LEN {
+            return Err(SerializationError::InvalidData);
+        }
+        let proof_hashes = (0..path_len)
+            .map(|_| <[u8; 32]>::deserialize_with_mode(&mut reader, compress, validate))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !leaf_hash.iter().chain(&proof_hashes).all(is_canonical_hash) {
+            return Err(SerializationError::InvalidData);
+        }
+
+        let proof = AnchoredProof {
+            key_id,
+            commitment,
+            modified_commitment,
+            p_point,
+            leaf_hash,
+            leaf_index: usize::try_from(leaf_index).map_err(|_| SerializationError::InvalidData)?,
+            merkle_proof: MerkleProof::new(proof_hashes),
+            dleq_proof: deserialize_dleq(&mut reader, compress, validate)?,
+            schnorr_proof: deserialize_schnorr(&mut reader, compress, validate)?,
+        };
+        if validate == Validate::Yes {
+            proof.check()?;
+        }
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{G1Affine, G1Projective};
+    use crate::test_utils::proof_and_context;
+    use crate::verify::verify_anchored_proof;
+
+    #[test]
+    fn test_round_trip_compressed_and_uncompressed() {
+        let (proof, context) = proof_and_context();
+
+        for compress in [Compress::Yes, Compress::No] {
+            let mut bytes = Vec::new();
+            proof.serialize_with_mode(&mut bytes, compress).unwrap();
+            assert_eq!(bytes.len(), proof.serialized_size(compress));
+            assert_eq!(bytes[0], PROOF_ENCODING_VERSION);
+
+            let decoded = AnchoredProof::<G1Projective>::deserialize_with_mode(&bytes[..], compress, Validate::Yes).unwrap();
+            assert_eq!(decoded.leaf_hash, proof.leaf_hash);
+            assert_eq!(decoded.leaf_index, proof.leaf_index);
+            assert_eq!(decoded.merkle_proof.proof_hashes(), proof.merkle_proof.proof_hashes());
+            assert_eq!(verify_anchored_proof(&decoded, &context), Ok(()));
+
+            let mut again = Vec::new();
+            decoded.serialize_with_mode(&mut again, compress).unwrap();
+            assert_eq!(bytes, again);
+        }
+        assert!(proof.compressed_size() < proof.uncompressed_size());
+    }
+
+    #[test]
+    fn test_omitted_leaf_hash_round_trips() {
+        let (mut proof, context) = proof_and_context();
+        let full_size = proof.compressed_size();
+        proof.leaf_hash = None;
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), full_size - 32);
+
+        let decoded = AnchoredProof::<G1Projective>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(decoded.leaf_hash, None);
+        assert_eq!(verify_anchored_proof(&decoded, &context), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_unknown_version() {
+        let (proof, _) = proof_and_context();
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+
+        bytes[0] = PROOF_ENCODING_VERSION + 1;
+        assert!(AnchoredProof::<G1Projective>::deserialize_compressed(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_out_of_field_hashes() {
+        let (mut proof, _) = proof_and_context();
+        let mut hashes = proof.merkle_proof.proof_hashes().to_vec();
+        hashes[0] = [0xff; 32];
+        proof.merkle_proof = MerkleProof::new(hashes);
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        assert!(AnchoredProof::<G1Projective>::deserialize_compressed(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_oversized_path_length() {
+        let (proof, _) = proof_and_context();
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+
+        // The path length follows the version byte, key id, three points, the
+        // leaf hash and the leaf index
+        let offset = 1 + 32 + 3 * proof.commitment.compressed_size() + proof.leaf_hash.compressed_size() + 8;
+        assert_eq!(bytes[offset..offset + 8], (proof.merkle_proof.proof_hashes().len() as u64).to_le_bytes());
+
+        bytes[offset..offset + 8].copy_from_slice(&(1u64 << 50).to_le_bytes());
+        assert!(AnchoredProof::<G1Projective>::deserialize_compressed(&bytes[..]).is_err());
+        bytes[offset..offset + 8].copy_from_slice(&(MAX_PATH_LEN + 1).to_le_bytes());
+        assert!(AnchoredProof::<G1Projective>::deserialize_compressed(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_identity_points() {
+        let (mut proof, _) = proof_and_context();
+        proof.schnorr_proof.commitment = G1Affine::zero();
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+
+        assert!(AnchoredProof::<G1Projective>::deserialize_compressed(&bytes[..]).is_err());
+        assert!(AnchoredProof::<G1Projective>::deserialize_compressed_unchecked(&bytes[..]).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_points_off_the_curve() {
+        let (proof, _) = proof_and_context();
+        let mut bytes = Vec::new();
+        proof.serialize_uncompressed(&mut bytes).unwrap();
+
+        // Perturb the y-coordinate of the commitment, right after the version
+        // byte and the key id
+        let point_len = proof.commitment.uncompressed_size();
+        bytes[1 + 32 + point_len - 1] ^= 1;
+        assert!(AnchoredProof::<G1Projective>::deserialize_uncompressed(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_sub_proofs_round_trip() {
+        let (proof, _) = proof_and_context();
+
+        let mut bytes = Vec::new();
+        proof.dleq_proof.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes[0], SUB_PROOF_ENCODING_VERSION);
+        assert_eq!(bytes.len(), proof.dleq_proof.compressed_size());
+        let dleq = DLEQProof::<G1Projective>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(dleq.r_commitment_1, proof.dleq_proof.r_commitment_1);
+        assert_eq!(dleq.response, proof.dleq_proof.response);
+
+        let mut bytes = Vec::new();
+        proof.schnorr_proof.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes[0], SUB_PROOF_ENCODING_VERSION);
+        assert_eq!(bytes.len(), proof.schnorr_proof.compressed_size());
+        let schnorr = SchnorrProof::<G1Projective>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(schnorr.commitment, proof.schnorr_proof.commitment);
+        assert_eq!(schnorr.response, proof.schnorr_proof.response);
+    }
+
+    #[test]
+    fn test_sub_proofs_reject_unknown_version() {
+        let (proof, _) = proof_and_context();
+
+        let mut bytes = Vec::new();
+        proof.dleq_proof.serialize_compressed(&mut bytes).unwrap();
+        bytes[0] = SUB_PROOF_ENCODING_VERSION + 1;
+        assert!(DLEQProof::<G1Projective>::deserialize_compressed(&bytes[..]).is_err());
+
+        let mut bytes = Vec::new();
+        proof.schnorr_proof.serialize_compressed(&mut bytes).unwrap();
+        bytes[0] = SUB_PROOF_ENCODING_VERSION + 1;
+        assert!(SchnorrProof::<G1Projective>::deserialize_compressed(&bytes[..]).is_err());
+    }
+}
@@ -1,58 +1,309 @@
-use ark_ff::{BigInteger, BigInteger256, PrimeField, UniformRand};
-use light_poseidon::{Poseidon, PoseidonHasher};
-use rs_merkle::MerkleTree;
-use ark_ec::{AffineRepr, CurveGroup, PrimeGroup, short_weierstrass::Affine};
-use ark_std::test_rng;
-use ark_bn254::{Fr, G1Affine, G1Projective, g1};
+//! Generators, secrets and anchored trees
+//!
+//! Tree builders take the same G the prover uses: `tree_setup` reads it from
+//! the parameters, and `tree_setup_range`, `tree_setup_set` and `range_points`
+//! take it as `generator`. Every leaf point is G*(x*a) = A_G*x, so
+//! `tree_setup_public` and `tree_setup_set_public` rebuild the same trees from
+//! the tree anchor A_G without the secret. With the `parallel` feature, points,
+//! leaf hashes and Merkle layers are built across threads; the roots are
+//! byte-identical to the serial build.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use ark_ff::UniformRand;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_bn254::{Fr, G1Affine};
+use rand::{CryptoRng, RngCore, rngs::OsRng};
 use sha2::{Digest, Sha256};
-use crate::{LEAVES_POSEIDON_DOMAIN, PoseidonMerkleHasher, split_fq_to_fr};
+use zeroize::Zeroizing;
+use crate::params::PublicParameters;
+use crate::{
+    AnchoredProofError, IndexedTree, LeafEncoding, PoseidonCurve, anchored_leaf_hash_with_encoding, padding_leaf_hash,
+};
 
+/// The generators of `PublicParameters::default()`: the standard BN254 G1
+/// generator, and H and B from RFC 9380 hash-to-curve
 pub fn generator_setup () -> (G1Affine, G1Affine, G1Affine){
-    let first = G1Affine::generator();
-    let second = sample_nums_generator(&[0; 32]);
-    let third = sample_nums_generator(&[1; 32]);
-    (first, second, third)
+    PublicParameters::default().generators()
+}
+
+/// Samples a fresh secret scalar from OS entropy, wiped when dropped
+pub fn secret_setup () -> Zeroizing<Fr> {
+    secret_setup_with_rng(&mut OsRng)
 }
 
-pub fn secret_setup () -> Fr {
-    let mut rng = test_rng();
-    Fr::rand(& mut rng)
+pub fn secret_setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Zeroizing<Fr> {
+    Zeroizing::new(Fr::rand(rng))
+}
+
+/// Deterministic RNG for reproducible tests and fixtures.
+/// Only available under `cfg(test)` or the `insecure-test-rng` feature.
+#[cfg(any(test, feature = "insecure-test-rng"))]
+pub fn seeded_rng(seed: u64) -> rand::rngs::StdRng {
+    use rand::SeedableRng;
+    rand::rngs::StdRng::seed_from_u64(seed)
+}
+
+pub fn anchor_setup<A: AffineRepr>(secret: &Zeroizing<A::ScalarField>, generator: &A) -> A {
+    ((*generator)*(**secret)).into_affine()
+}
+
+/// Inclusive range `[lo, hi]` of values committed to by a tree
+///
+/// Value `x` sits at leaf index `x - lo`; the leaves after `hi - lo` up to the
+/// next power of two are padding that no witness can open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValueRange {
+    pub lo: u64,
+    pub hi: u64,
+}
+
+// A valid range always holds at least one value, so there is no `is_empty`
+#[allow(clippy::len_without_is_empty)]
+impl ValueRange {
+    /// `lo` must be at least 1: the value 0 maps P to the point at infinity
+    pub fn new(lo: u64, hi: u64) -> Result<Self, AnchoredProofError> {
+        if lo == 0 || lo > hi || hi - lo > (usize::MAX >> 1) as u64 {
+            return Err(AnchoredProofError::InvalidRange);
+        }
+        Ok(ValueRange { lo, hi })
+    }
+
+    /// The range 1..=2^bits
+    pub fn power_of_two(bits: u8) -> Result<Self, AnchoredProofError> {
+        let hi = 1u64.checked_shl(bits.into()).ok_or(AnchoredProofError::InvalidRange)?;
+        ValueRange::new(1, hi)
+    }
+
+    /// Number of values in the range
+    pub fn len(&self) -> usize {
+        (self.hi - self.lo) as usize + 1
+    }
+
+    /// Number of leaves in the padded tree
+    pub fn leaves_len(&self) -> usize {
+        self.len().next_power_of_two()
+    }
+
+    pub fn index_of(&self, value: u64) -> Option<usize> {
+        (self.lo..=self.hi).contains(&value).then(|| (value - self.lo) as usize)
+    }
+
+    pub fn value_at(&self, index: usize) -> Option<u64> {
+        (index < self.len()).then(|| self.lo + index as u64)
+    }
+}
+
+/// Builds the anchored tree over 1..=2^range with the parameters' G
+pub fn tree_setup(
+    params: &PublicParameters,
+    range: u8,
+    anchor: &G1Affine,
+    a: &Zeroizing<Fr>,
+) -> Result<IndexedTree, AnchoredProofError> {
+    tree_setup_range(ValueRange::power_of_two(range)?, &params.generator_g.point, anchor, a)
+}
+
+/// Builds the anchored tree over `range`, padded to a power of two.
+/// `generator` must be the G the prover uses for P.
+pub fn tree_setup_range<A>(
+    range: ValueRange,
+    generator: &A,
+    anchor: &A,
+    a: &Zeroizing<A::ScalarField>,
+) -> Result<IndexedTree, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    tree_setup_range_with_encoding(range, generator, anchor, a, LeafEncoding::Full)
+}
+
+/// Builds the range tree with the given leaf encoding; `LeafEncoding::LegacyXOnly`
+/// reproduces roots published before y-parity was bound into leaves
+pub fn tree_setup_range_with_encoding<A>(
+    range: ValueRange,
+    generator: &A,
+    anchor: &A,
+    a: &Zeroizing<A::ScalarField>,
+    encoding: LeafEncoding,
+) -> Result<IndexedTree, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    anchored_tree(&range_points(range, generator, a), anchor, encoding)
+}
+
+/// Builds the anchored tree over `range` from the public tree anchor A_G = G*a,
+/// without the secret a. Check A_G with `tree_anchor::verify_tree_anchor` first.
+pub fn tree_setup_public<A>(range: ValueRange, anchor: &A, a_g: &A) -> Result<IndexedTree, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    tree_setup_public_with_encoding(range, anchor, a_g, LeafEncoding::Full)
+}
+
+pub fn tree_setup_public_with_encoding<A>(
+    range: ValueRange,
+    anchor: &A,
+    a_g: &A,
+    encoding: LeafEncoding,
+) -> Result<IndexedTree, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    anchored_tree(&stepped_points(range, a_g.into_group()), anchor, encoding)
+}
+
+/// P_x = G*(x*a) for every x in `range`
+///
+/// Only P_lo needs a scalar multiplication: P_{x+1} = P_x + G*a, and the
+/// projective results are normalized to affine with a single batch inversion.
+pub fn range_points<A: AffineRepr>(range: ValueRange, generator: &A, a: &Zeroizing<A::ScalarField>) -> Vec<A> {
+    stepped_points(range, *generator * **a)
+}
+
+/// step*x for every x in `range`
+fn stepped_points<A: AffineRepr>(range: ValueRange, step: A::Group) -> Vec<A> {
+    // Each thread walks its own chunk of the range from one scalar multiplication
+    #[cfg(feature = "parallel")]
+    {
+        let chunk_len = range.len().div_ceil(rayon::current_num_threads());
+        let offsets: Vec<usize> = (0..range.len()).step_by(chunk_len).collect();
+        let chunks: Vec<Vec<A>> = offsets
+            .into_par_iter()
+            .map(|offset| incremental_points(step, range.lo + offset as u64, chunk_len.min(range.len() - offset)))
+            .collect();
+        chunks.concat()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    incremental_points(step, range.lo, range.len())
+}
+
+fn incremental_points<A: AffineRepr>(step: A::Group, first: u64, len: usize) -> Vec<A> {
+    let mut current = step * A::ScalarField::from(first);
+
+    let mut points = Vec::with_capacity(len);
+    for _ in 0..len {
+        points.push(current);
+        current += step;
+    }
+    A::Group::normalize_batch(&points)
+}
+
+/// Hashes one anchored leaf per point and pads with filler leaves to a power of two
+fn anchored_tree<A>(points: &[A], anchor: &A, encoding: LeafEncoding) -> Result<IndexedTree, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+{
+    let leaves_len = points.len().next_power_of_two();
+
+    #[cfg(feature = "parallel")]
+    let points_iter = points.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let points_iter = points.iter();
+
+    let mut leaves: Vec<[u8; 32]> = points_iter
+        .map(|p| anchored_leaf_hash_with_encoding(anchor, p, encoding))
+        .collect::<Result<_, _>>()?;
+
+    for index in points.len()..leaves_len {
+        leaves.push(padding_leaf_hash(index)?);
+    }
+    Ok(IndexedTree::from_leaves(&leaves))
 }
 
-pub fn anchor_setup (secret: &Fr, generator: &G1Affine) -> G1Affine {
-    ((*generator)*(*secret)).into_affine()
+/// Anchored tree over an arbitrary allow-list of values
+pub struct SetTree<F = Fr> {
+    pub tree: IndexedTree,
+    pub indices: HashMap<F, usize>,
 }
 
-pub fn tree_setup(range: u8, anchor: &G1Affine, a: &Fr) -> MerkleTree<PoseidonMerkleHasher> {
-    let anchor_x_limbs = split_fq_to_fr(&anchor.x().unwrap());
+// A set tree always holds at least one value, so there is no `is_empty`
+#[allow(clippy::len_without_is_empty)]
+impl<F: Hash + Eq> SetTree<F> {
+    pub fn index_of(&self, value: &F) -> Option<usize> {
+        self.indices.get(value).copied()
+    }
 
-    let mut x = BigInteger256::one();
-    let mut count: BigInteger256 = BigInteger256::one();
+    /// Number of distinct values in the set
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+}
 
-    count = count << range.into();
-    let mut leaves: Vec<[u8; 32]> = Vec::new();
+/// Builds the anchored tree over `values` in iteration order, padded to a power of two.
+/// Repeated values keep their first position; the value 0 maps P to the
+/// point at infinity and is rejected with `PointAtInfinity`.
+pub fn tree_setup_set<A, I>(
+    values: I,
+    generator: &A,
+    anchor: &A,
+    a: &Zeroizing<A::ScalarField>,
+) -> Result<SetTree<A::ScalarField>, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+    I: IntoIterator<Item = A::ScalarField>,
+{
+    set_tree(values, anchor, *generator * **a, LeafEncoding::Full)
+}
+
+/// Builds the set tree from the public tree anchor A_G, like `tree_setup_public`
+pub fn tree_setup_set_public<A, I>(values: I, anchor: &A, a_g: &A) -> Result<SetTree<A::ScalarField>, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+    I: IntoIterator<Item = A::ScalarField>,
+{
+    set_tree(values, anchor, a_g.into_group(), LeafEncoding::Full)
+}
 
-    while x <= count{
-        let x_fr = Fr::from(x);
-        let scalar = x_fr * a;
-        let p: Affine<g1::Config> = (G1Projective::generator() * scalar).into();
-        let p_x_limbs = split_fq_to_fr(&p.x().unwrap());
-        
-        let mut poseidon = Poseidon::<Fr>::new_circom(5).unwrap();
-        let hash = poseidon.hash(&[
-            Fr::from(LEAVES_POSEIDON_DOMAIN), 
-            anchor_x_limbs[0], anchor_x_limbs[1], p_x_limbs[0], p_x_limbs[1]]).unwrap();
+/// Set tree over the points step*value
+pub(crate) fn set_tree<A, I>(
+    values: I,
+    anchor: &A,
+    step: A::Group,
+    encoding: LeafEncoding,
+) -> Result<SetTree<A::ScalarField>, AnchoredProofError>
+where
+    A: AffineRepr<Group: PoseidonCurve>,
+    I: IntoIterator<Item = A::ScalarField>,
+{
+    let mut indices = HashMap::new();
+    let mut distinct = Vec::new();
 
-        let mut bytes = [0u8; 32];
-        let v = hash.into_bigint().to_bytes_be();
-        bytes[32 - v.len()..].copy_from_slice(&v);
-        leaves.push(bytes);
-        x.add_with_carry(&BigInteger256::one());
+    for value in values {
+        if indices.contains_key(&value) {
+            continue;
+        }
+        indices.insert(value, distinct.len());
+        distinct.push(value);
+    }
+    if distinct.is_empty() {
+        return Err(AnchoredProofError::InvalidRange);
     }
-    MerkleTree::<PoseidonMerkleHasher>::from_leaves(&leaves)
+
+    #[cfg(feature = "parallel")]
+    let values_iter = distinct.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let values_iter = distinct.iter();
+
+    let points: Vec<A::Group> = values_iter
+        .map(|value| step * value)
+        .collect();
+
+    Ok(SetTree {
+        tree: anchored_tree(&A::Group::normalize_batch(&points), anchor, encoding)?,
+        indices,
+    })
 }
 
-fn sample_nums_generator(seed: &[u8]) -> G1Affine {
+/// Hashes `seed` with a counter until the digest decodes to a point, then
+/// clears the cofactor so the point lies in the prime-order subgroup.
+/// Not a standard encoding: BN254 G1 generators come from `hash_to_curve`.
+pub(crate) fn sample_nums_generator<A: AffineRepr>(seed: &[u8]) -> A {
     let mut counter = 0u64;
     
     loop {
@@ -61,11 +312,169 @@ fn sample_nums_generator(seed: &[u8]) -> G1Affine {
         hasher.update(counter.to_be_bytes());
         let hash = hasher.finalize();
 
-        if let Some(point) = G1Affine::from_random_bytes(&hash) {
-            if !point.is_zero() {
-                return point;
-            }
+        if let Some(point) = A::from_random_bytes(&hash).map(|p| p.clear_cofactor())
+            && !point.is_zero()
+        {
+            return point;
         }
         counter += 1;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::G1Projective;
+    use ark_ec::PrimeGroup;
+    use crate::anchored_leaf_hash;
+
+    #[test]
+    fn test_value_range_mapping() {
+        let range = ValueRange::new(18, 120).unwrap();
+        assert_eq!(range.len(), 103);
+        assert_eq!(range.leaves_len(), 128);
+
+        assert_eq!(range.index_of(18), Some(0));
+        assert_eq!(range.index_of(120), Some(102));
+        assert_eq!(range.index_of(17), None);
+        assert_eq!(range.index_of(121), None);
+
+        assert_eq!(range.value_at(0), Some(18));
+        assert_eq!(range.value_at(102), Some(120));
+        assert_eq!(range.value_at(103), None);
+    }
+
+    #[test]
+    fn test_value_range_bounds() {
+        assert_eq!(ValueRange::new(0, 5), Err(AnchoredProofError::InvalidRange));
+        assert_eq!(ValueRange::new(6, 5), Err(AnchoredProofError::InvalidRange));
+        assert_eq!(ValueRange::power_of_two(64), Err(AnchoredProofError::InvalidRange));
+        assert_eq!(ValueRange::power_of_two(3), ValueRange::new(1, 8));
+        assert_eq!(ValueRange::new(7, 7).unwrap().leaves_len(), 1);
+    }
+
+    #[test]
+    fn test_range_tree_is_padded() {
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+        let range = ValueRange::new(1000, 1004).unwrap();
+
+        let tree = tree_setup_range(range, &g, &anchor, &secret).unwrap();
+        let leaves = tree.leaves().unwrap();
+        assert_eq!(leaves.len(), 8);
+
+        let p = (G1Affine::generator() * (Fr::from(1003u64) * *secret)).into_affine();
+        assert_eq!(leaves[range.index_of(1003).unwrap()], anchored_leaf_hash(&anchor, &p).unwrap());
+        for (index, leaf) in leaves.iter().enumerate().skip(range.len()) {
+            assert_eq!(*leaf, padding_leaf_hash(index).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_set_tree_lookup() {
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+        let values = [3u64, 7, 42, 7, 1001].map(Fr::from);
+
+        let set = tree_setup_set(values, &g, &anchor, &secret).unwrap();
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.tree.leaves_len(), 4);
+        assert_eq!(set.index_of(&Fr::from(3u64)), Some(0));
+        assert_eq!(set.index_of(&Fr::from(42u64)), Some(2));
+        assert_eq!(set.index_of(&Fr::from(1001u64)), Some(3));
+        assert_eq!(set.index_of(&Fr::from(8u64)), None);
+
+        assert!(matches!(tree_setup_set([], &g, &anchor, &secret), Err(AnchoredProofError::InvalidRange)));
+        assert!(matches!(
+            tree_setup_set([Fr::from(0u64)], &g, &anchor, &secret),
+            Err(AnchoredProofError::PointAtInfinity)
+        ));
+    }
+
+    #[test]
+    fn test_range_points_match_scalar_multiplication() {
+        let secret = secret_setup();
+        let range = ValueRange::new(5, 40).unwrap();
+
+        let points: Vec<G1Affine> = range_points(range, &G1Affine::generator(), &secret);
+        assert_eq!(points.len(), range.len());
+        for (index, point) in points.iter().enumerate() {
+            let x = Fr::from(range.value_at(index).unwrap());
+            assert_eq!(*point, (G1Projective::generator() * (x * *secret)).into_affine());
+        }
+    }
+
+    #[test]
+    fn test_tree_matches_per_leaf_reference() {
+        use rs_merkle::MerkleTree;
+        use crate::PoseidonMerkleHasher;
+
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+        // An odd length exercises uneven chunks when built in parallel
+        let range = ValueRange::new(3, 203).unwrap();
+
+        let mut leaves: Vec<[u8; 32]> = (range.lo..=range.hi)
+            .map(|x| {
+                let p = (G1Projective::generator() * (Fr::from(x) * *secret)).into_affine();
+                anchored_leaf_hash(&anchor, &p).unwrap()
+            })
+            .collect();
+        for index in leaves.len()..range.leaves_len() {
+            leaves.push(padding_leaf_hash(index).unwrap());
+        }
+        let reference = MerkleTree::<PoseidonMerkleHasher>::from_leaves(&leaves);
+
+        #[cfg(feature = "parallel")]
+        let tree = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(|| tree_setup_range(range, &g, &anchor, &secret))
+            .unwrap();
+        #[cfg(not(feature = "parallel"))]
+        let tree = tree_setup_range(range, &g, &anchor, &secret).unwrap();
+        assert_eq!(tree.leaves().unwrap(), leaves);
+        assert_eq!(tree.root(), reference.root());
+    }
+
+    #[test]
+    fn test_legacy_tree_hashes_x_only() {
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+        let range = ValueRange::new(1, 4).unwrap();
+
+        let legacy = tree_setup_range_with_encoding(range, &g, &anchor, &secret, LeafEncoding::LegacyXOnly).unwrap();
+        let p = (G1Projective::generator() * *secret).into_affine();
+        let leaf = anchored_leaf_hash_with_encoding(&anchor, &p, LeafEncoding::LegacyXOnly).unwrap();
+        assert_eq!(legacy.leaves().unwrap()[0], leaf);
+        assert_ne!(legacy.root(), tree_setup_range(range, &g, &anchor, &secret).unwrap().root());
+    }
+
+    #[test]
+    fn test_public_tree_matches_secret_tree() {
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+        let a_g = (g * *secret).into_affine();
+        let range = ValueRange::new(7, 29).unwrap();
+
+        let public = tree_setup_public(range, &anchor, &a_g).unwrap();
+        assert_eq!(public.root(), tree_setup_range(range, &g, &anchor, &secret).unwrap().root());
+    }
+
+    #[test]
+    fn test_power_of_two_tree_matches_range_tree() {
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+
+        let tree = tree_setup(&PublicParameters::default(), 3, &anchor, &secret).unwrap();
+        let range_tree = tree_setup_range(ValueRange::new(1, 8).unwrap(), &g, &anchor, &secret).unwrap();
+        assert_eq!(tree.root(), range_tree.root());
+    }
+}
@@ -0,0 +1,97 @@
+//! Fixtures shared by the unit tests
+
+use ark_bn254::G1Projective;
+use ark_ff::UniformRand;
+use rand::rngs::OsRng;
+use zeroize::Zeroizing;
+
+use crate::prove::generate_anchored_proof;
+use crate::setup::{ValueRange, anchor_setup, tree_setup_range};
+use crate::verify::VerificationContext;
+use crate::{AnchoredProof, AnchoredProofError, IndexedTree, LeafEncoding, PoseidonCurve, ProofInput, PublicParameters};
+
+/// An issuer's secret, anchor and range tree, and a holder's blinding factor
+pub(crate) struct Fixture<C: PoseidonCurve = G1Projective> {
+    pub g: C::Affine,
+    pub h: C::Affine,
+    pub b: C::Affine,
+    pub secret: Zeroizing<C::ScalarField>,
+    pub blinding: Zeroizing<C::ScalarField>,
+    pub anchor: C::Affine,
+    pub tree: IndexedTree,
+}
+
+impl Fixture {
+    /// BN254 fixture over the default parameters and the values 1..=2^range
+    pub fn new(range: u8) -> Self {
+        Self::with_params(&PublicParameters::default(), range)
+    }
+
+    pub fn with_params(params: &PublicParameters, range: u8) -> Self {
+        Self::with_generators(params.generators(), ValueRange::power_of_two(range).unwrap())
+    }
+}
+
+impl<C: PoseidonCurve> Fixture<C> {
+    /// Fixture with fresh random secret and blinding
+    pub fn with_generators(generators: (C::Affine, C::Affine, C::Affine), range: ValueRange) -> Self {
+        let secret = Zeroizing::new(C::ScalarField::rand(&mut OsRng));
+        let blinding = Zeroizing::new(C::ScalarField::rand(&mut OsRng));
+        Self::with_secrets(generators, range, secret, blinding)
+    }
+
+    pub fn with_secrets(
+        (g, h, b): (C::Affine, C::Affine, C::Affine),
+        range: ValueRange,
+        secret: Zeroizing<C::ScalarField>,
+        blinding: Zeroizing<C::ScalarField>,
+    ) -> Self {
+        let anchor = anchor_setup(&secret, &b);
+        let tree = tree_setup_range(range, &g, &anchor, &secret).unwrap();
+        Fixture { g, h, b, secret, blinding, anchor, tree }
+    }
+
+    /// Proof input for `witness` with an empty context
+    pub fn input<'a>(&'a self, witness: &'a Zeroizing<C::ScalarField>) -> ProofInput<'a, C> {
+        ProofInput {
+            secret: &self.secret,
+            witness,
+            blinding: &self.blinding,
+            generator_g: &self.g,
+            generator_h: &self.h,
+            generator_b: &self.b,
+            anchor: &self.anchor,
+            tree: &self.tree,
+            context: &[],
+            leaf_encoding: LeafEncoding::Full,
+        }
+    }
+
+    pub fn try_prove(&self, witness: u64) -> Result<AnchoredProof<C>, AnchoredProofError> {
+        let witness = Zeroizing::new(C::ScalarField::from(witness));
+        generate_anchored_proof(self.input(&witness))
+    }
+
+    pub fn prove(&self, witness: u64) -> AnchoredProof<C> {
+        self.try_prove(witness).unwrap()
+    }
+
+    pub fn context(&self) -> VerificationContext<C> {
+        VerificationContext {
+            generator_g: self.g,
+            generator_h: self.h,
+            generator_b: self.b,
+            anchor: self.anchor,
+            tree_root: self.tree.root().unwrap(),
+            leaves_len: self.tree.leaves_len(),
+            context: Vec::new(),
+            leaf_encoding: LeafEncoding::Full,
+        }
+    }
+}
+
+/// A proof for 4 in a tree over 1..=8, with the context it verifies under
+pub(crate) fn proof_and_context() -> (AnchoredProof, VerificationContext) {
+    let fixture = Fixture::new(3);
+    (fixture.prove(4), fixture.context())
+}
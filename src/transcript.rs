@@ -0,0 +1,171 @@
+//! Fiat–Shamir transcript over Poseidon
+//!
+//! Both sub-proofs hash the whole statement before their own commitments: the
+//! protocol label, a per-proof domain constant, the generators G, H and B, the
+//! anchor, C, C', P, the Merkle root and size, and the caller's context bytes
+//! (`ProofInput::context`, `VerificationContext::context`), so a proof made for
+//! one context does not verify under another.
+//! Points enter through `PoseidonCurve::point_to_fields`, the same encoding
+//! as anchored leaves, so the y-parity is bound along with x.
+//!
+//! Poseidon takes at most `POSEIDON_MAX_INPUTS` elements, so the challenge is
+//! a chain: starting from the domain constant, each chunk of up to `RATE`
+//! absorbed elements is hashed together with the running state.
+
+use ark_bn254::Fr;
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::poseidon::poseidon_hash;
+use crate::{AnchoredProofError, PoseidonCurve};
+
+/// Protocol label absorbed first by every transcript
+pub const TRANSCRIPT_LABEL: &[u8] = b"anchored-merkle-proof/fiat-shamir/v1";
+
+/// Largest input count of the circom Poseidon instances
+pub const POSEIDON_MAX_INPUTS: usize = 12;
+
+/// New elements per permutation; one input always carries the running state
+const RATE: usize = POSEIDON_MAX_INPUTS - 1;
+
+/// Byte strings enter as 16-byte little-endian limbs, below the field modulus
+const LIMB_BYTES: usize = 16;
+
+pub struct Transcript {
+    domain: u64,
+    elements: Vec<Fr>,
+}
+
+impl Transcript {
+    /// A transcript for the sub-proof identified by `domain`
+    pub fn new(domain: u64) -> Self {
+        let mut transcript = Transcript { domain, elements: Vec::new() };
+        transcript.absorb_bytes(TRANSCRIPT_LABEL);
+        transcript
+    }
+
+    pub fn absorb_u64(&mut self, value: u64) -> &mut Self {
+        self.elements.push(Fr::from(value));
+        self
+    }
+
+    /// Length-prefixed, so consecutive byte strings cannot run into each other
+    pub fn absorb_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.absorb_u64(bytes.len() as u64);
+        self.elements.extend(bytes.chunks(LIMB_BYTES).map(Fr::from_le_bytes_mod_order));
+        self
+    }
+
+    /// Absorbs the canonical field encoding of `point`, including its y-parity
+    pub fn absorb_point<A>(&mut self, point: &A) -> Result<&mut Self, AnchoredProofError>
+    where
+        A: AffineRepr<Group: PoseidonCurve>,
+    {
+        self.elements.extend(A::Group::point_to_fields(point)?);
+        Ok(self)
+    }
+
+    /// The domain and every absorbed element as big-endian bytes, so that a
+    /// nonce can be derived from exactly what the challenge will hash
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = self.domain.to_be_bytes().to_vec();
+        encoded.extend((self.elements.len() as u64).to_be_bytes());
+        for element in &self.elements {
+            encoded.extend(element.into_bigint().to_bytes_be());
+        }
+        encoded
+    }
+
+    /// Hashes everything absorbed so far into a challenge scalar of `C`
+    pub fn challenge<C: PoseidonCurve>(&self) -> Result<C::ScalarField, AnchoredProofError> {
+        let mut state = Fr::from(self.domain);
+        for chunk in self.elements.chunks(RATE) {
+            state = poseidon_hash(&[&[state], chunk].concat())?;
+        }
+        Ok(C::challenge_scalar(&state))
+    }
+}
+
+/// The public statement both sub-proofs are bound to
+pub(crate) struct Statement<'a, C: PoseidonCurve> {
+    pub generator_g: &'a C::Affine,
+    pub generator_h: &'a C::Affine,
+    pub generator_b: &'a C::Affine,
+    pub anchor: &'a C::Affine,
+    pub commitment: &'a C::Affine,
+    pub modified_commitment: &'a C::Affine,
+    pub p_point: &'a C::Affine,
+    pub tree_root: &'a [u8; 32],
+    pub leaves_len: usize,
+    pub context: &'a [u8],
+}
+
+impl<C: PoseidonCurve> Statement<'_, C> {
+    /// A transcript for `domain` that has absorbed the whole statement
+    pub fn transcript(&self, domain: u64) -> Result<Transcript, AnchoredProofError> {
+        let mut transcript = Transcript::new(domain);
+        for point in [self.generator_g, self.generator_h, self.generator_b] {
+            transcript.absorb_point(point)?;
+        }
+        for point in [self.anchor, self.commitment, self.modified_commitment, self.p_point] {
+            transcript.absorb_point(point)?;
+        }
+        transcript
+            .absorb_bytes(self.tree_root)
+            .absorb_u64(self.leaves_len as u64)
+            .absorb_bytes(self.context);
+        Ok(transcript)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{G1Affine, G1Projective};
+    use crate::{DLEQ_POSEIDON_DOMAIN, SCHNORR_POSEIDON_DOMAIN};
+
+    fn challenge(transcript: &Transcript) -> ark_bn254::Fr {
+        transcript.challenge::<G1Projective>().unwrap()
+    }
+
+    #[test]
+    fn test_domains_separate_challenges() {
+        let point = G1Affine::generator();
+        let mut dleq = Transcript::new(DLEQ_POSEIDON_DOMAIN);
+        let mut schnorr = Transcript::new(SCHNORR_POSEIDON_DOMAIN);
+        dleq.absorb_point(&point).unwrap();
+        schnorr.absorb_point(&point).unwrap();
+        assert_ne!(challenge(&dleq), challenge(&schnorr));
+    }
+
+    #[test]
+    fn test_binds_y_parity() {
+        // P and -P share their x-coordinate
+        let point = G1Affine::generator();
+        let mut positive = Transcript::new(DLEQ_POSEIDON_DOMAIN);
+        let mut negative = Transcript::new(DLEQ_POSEIDON_DOMAIN);
+        positive.absorb_point(&point).unwrap();
+        negative.absorb_point(&-point).unwrap();
+        assert_ne!(challenge(&positive), challenge(&negative));
+    }
+
+    #[test]
+    fn test_byte_strings_are_length_prefixed() {
+        let mut split = Transcript::new(DLEQ_POSEIDON_DOMAIN);
+        let mut joined = Transcript::new(DLEQ_POSEIDON_DOMAIN);
+        split.absorb_bytes(b"ab").absorb_bytes(b"c");
+        joined.absorb_bytes(b"a").absorb_bytes(b"bc");
+        assert_ne!(challenge(&split), challenge(&joined));
+    }
+
+    #[test]
+    fn test_long_transcripts_chain_past_the_arity_limit() {
+        let mut transcript = Transcript::new(DLEQ_POSEIDON_DOMAIN);
+        for i in 0..(3 * RATE as u64) {
+            transcript.absorb_u64(i);
+        }
+        let before = challenge(&transcript);
+        transcript.absorb_u64(0);
+        assert_ne!(before, challenge(&transcript));
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rs_merkle::{Hasher, MerkleProof};
+
+use crate::{AnchoredProofError, PoseidonMerkleHasher, is_canonical_hash};
+
+/// Merkle tree that also maps every leaf hash to its position,
+/// so the prover finds its leaf without scanning the tree
+///
+/// Layers are built with the same pairing rule as `rs_merkle::MerkleTree`
+/// (an unpaired last node is promoted unchanged), so roots and proofs are
+/// interchangeable with it. With the `parallel` feature each layer is hashed
+/// across threads.
+pub struct IndexedTree {
+    layers: Vec<Vec<[u8; 32]>>,
+    leaf_indices: HashMap<[u8; 32], usize>,
+}
+
+impl IndexedTree {
+    /// Builds the tree over leaves that are canonical field elements, as every
+    /// anchored and padding leaf is; use `try_from_leaves` for leaves read from
+    /// outside the crate
+    pub fn from_leaves(leaves: &[[u8; 32]]) -> Self {
+        let mut leaf_indices = HashMap::with_capacity(leaves.len());
+        for (index, leaf) in leaves.iter().enumerate() {
+            leaf_indices.entry(*leaf).or_insert(index);
+        }
+
+        let mut layers = Vec::new();
+        if !leaves.is_empty() {
+            layers.push(leaves.to_vec());
+            while let Some(layer) = layers.last().filter(|layer| layer.len() > 1) {
+                let parents = hash_layer(layer);
+                layers.push(parents);
+            }
+        }
+
+        IndexedTree { layers, leaf_indices }
+    }
+
+    /// Like `from_leaves`, but rejects leaves at or above the field modulus
+    /// before they reach Poseidon
+    pub fn try_from_leaves(leaves: &[[u8; 32]]) -> Result<Self, AnchoredProofError> {
+        if !leaves.iter().all(is_canonical_hash) {
+            return Err(AnchoredProofError::InvalidEncoding("leaf: not below the field modulus".to_string()));
+        }
+        Ok(Self::from_leaves(leaves))
+    }
+
+    /// Position of the first leaf equal to `leaf`
+    pub fn index_of_leaf(&self, leaf: &[u8; 32]) -> Option<usize> {
+        self.leaf_indices.get(leaf).copied()
+    }
+
+    pub fn root(&self) -> Option<[u8; 32]> {
+        self.layers.last().map(|layer| layer[0])
+    }
+
+    pub fn leaves(&self) -> Option<Vec<[u8; 32]>> {
+        self.layers.first().cloned()
+    }
+
+    pub fn leaves_len(&self) -> usize {
+        self.layers.first().map_or(0, Vec::len)
+    }
+
+    /// Number of layers between the leaves and the root
+    pub fn depth(&self) -> usize {
+        self.layers.len().saturating_sub(1)
+    }
+
+    /// Sibling hashes from the leaf at `leaf_index` up to the root, or `None`
+    /// when the tree has no such leaf
+    pub fn proof(&self, leaf_index: usize) -> Option<MerkleProof<PoseidonMerkleHasher>> {
+        if leaf_index >= self.leaves_len() {
+            return None;
+        }
+
+        let mut hashes = Vec::with_capacity(self.depth());
+        let mut index = leaf_index;
+
+        for layer in self.layers.iter().take(self.depth()) {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                hashes.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(MerkleProof::new(hashes))
+    }
+}
+
+fn hash_layer(nodes: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    #[cfg(feature = "parallel")]
+    let pairs = nodes.par_chunks(2);
+    #[cfg(not(feature = "parallel"))]
+    let pairs = nodes.chunks(2);
+
+    pairs
+        .map(|pair| PoseidonMerkleHasher::concat_and_hash(&pair[0], pair.get(1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_merkle::MerkleTree;
+
+    fn leaves(count: usize) -> Vec<[u8; 32]> {
+        (0..count).map(|i| crate::padding_leaf_hash(i).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_index_matches_leaf_positions() {
+        let leaves = leaves(6);
+        let tree = IndexedTree::from_leaves(&leaves);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            assert_eq!(tree.index_of_leaf(leaf), Some(index));
+        }
+        assert_eq!(tree.index_of_leaf(&[9; 32]), None);
+    }
+
+    #[test]
+    fn test_duplicate_leaves_resolve_to_first_position() {
+        let leaves = [[1; 32], [2; 32], [1; 32], [3; 32]];
+        let tree = IndexedTree::from_leaves(&leaves);
+        assert_eq!(tree.index_of_leaf(&[1; 32]), Some(0));
+    }
+
+    #[test]
+    fn test_matches_rs_merkle_roots_and_proofs() {
+        for count in 1..=17 {
+            let leaves = leaves(count);
+            let tree = IndexedTree::from_leaves(&leaves);
+            let reference = MerkleTree::<PoseidonMerkleHasher>::from_leaves(&leaves);
+
+            assert_eq!(tree.root(), reference.root(), "root for {count} leaves");
+
+            for index in 0..leaves.len() {
+                let proof = tree.proof(index).unwrap();
+                assert_eq!(proof.proof_hashes(), reference.proof(&[index]).proof_hashes());
+                assert!(proof.verify(tree.root().unwrap(), &[index], &[leaves[index]], leaves.len()));
+            }
+            assert!(tree.proof(leaves.len()).is_none(), "proof past {count} leaves");
+        }
+    }
+
+    #[test]
+    fn test_rejects_out_of_field_leaves() {
+        let out_of_field = [[0xff; 32], [1; 32]];
+        assert!(matches!(IndexedTree::try_from_leaves(&out_of_field), Err(AnchoredProofError::InvalidEncoding(_))));
+
+        let leaves = leaves(4);
+        assert_eq!(IndexedTree::try_from_leaves(&leaves).unwrap().root(), IndexedTree::from_leaves(&leaves).root());
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let tree = IndexedTree::from_leaves(&[]);
+        assert_eq!(tree.root(), None);
+        assert_eq!(tree.leaves(), None);
+        assert_eq!(tree.leaves_len(), 0);
+        assert!(tree.proof(0).is_none());
+    }
+}
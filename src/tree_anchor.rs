@@ -0,0 +1,131 @@
+//! Tree anchors: A_G = G*a, the public half of the tree secret
+//!
+//! Every point of a range tree is G*(x*a) = A_G*x, so anyone holding A_G can
+//! rebuild the tree (`setup::tree_setup_public`) without the secret. The issuer
+//! publishes A_G with a DLEQ proof that log_G(A_G) = log_B(U): the tree was
+//! built with the same secret that the anchor U, and so every proof, is bound to.
+//! The CLI `setup` writes A_G and its proof into `anchor.json`.
+
+use ark_bn254::G1Projective;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::UniformRand;
+use ark_serialize::Valid;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::transcript::Transcript;
+use crate::{AnchoredProofError, DLEQProof, PoseidonCurve, TREE_ANCHOR_POSEIDON_DOMAIN};
+
+/// A_G together with the proof that it shares its discrete log with the anchor
+pub struct TreeAnchor<C: CurveGroup = G1Projective> {
+    pub point: C::Affine,
+    pub proof: DLEQProof<C>,
+}
+
+/// Computes A_G = G*secret and proves log_G(A_G) = log_B(B*secret)
+pub fn tree_anchor_setup<C: PoseidonCurve, R: RngCore + CryptoRng>(
+    secret: &Zeroizing<C::ScalarField>,
+    generator_g: &C::Affine,
+    generator_b: &C::Affine,
+    rng: &mut R,
+) -> Result<TreeAnchor<C>, AnchoredProofError> {
+    let anchor = (*generator_b * **secret).into_affine();
+    let point = (*generator_g * **secret).into_affine();
+
+    let r = Zeroizing::new(C::ScalarField::rand(rng));
+    let r1_affine = (*generator_g * *r).into_affine();
+    let r2_affine = (*generator_b * *r).into_affine();
+
+    let challenge = challenge::<C>(generator_g, generator_b, &anchor, &point, &r1_affine, &r2_affine)?;
+    let response = *r + challenge * **secret;
+
+    Ok(TreeAnchor {
+        point,
+        proof: DLEQProof { r_commitment_1: r1_affine, r_commitment_2: r2_affine, response },
+    })
+}
+
+/// Checks that `tree_anchor` was made with the secret behind `anchor`
+pub fn verify_tree_anchor<C: PoseidonCurve>(
+    tree_anchor: &TreeAnchor<C>,
+    generator_g: &C::Affine,
+    generator_b: &C::Affine,
+    anchor: &C::Affine,
+) -> Result<(), AnchoredProofError> {
+    let point = &tree_anchor.point;
+    let proof = &tree_anchor.proof;
+    if point.is_zero() || point.check().is_err() {
+        return Err(AnchoredProofError::InvalidTreeAnchor);
+    }
+
+    let challenge = challenge::<C>(
+        generator_g,
+        generator_b,
+        anchor,
+        point,
+        &proof.r_commitment_1,
+        &proof.r_commitment_2,
+    )?;
+
+    // G*z = R1 + A_G*challenge and B*z = R2 + U*challenge
+    let check1 = *generator_g * proof.response == proof.r_commitment_1 + *point * challenge;
+    let check2 = *generator_b * proof.response == proof.r_commitment_2 + *anchor * challenge;
+
+    if check1 && check2 {
+        Ok(())
+    } else {
+        Err(AnchoredProofError::InvalidTreeAnchor)
+    }
+}
+
+fn challenge<C: PoseidonCurve>(
+    generator_g: &C::Affine,
+    generator_b: &C::Affine,
+    anchor: &C::Affine,
+    point: &C::Affine,
+    r_commitment_1: &C::Affine,
+    r_commitment_2: &C::Affine,
+) -> Result<C::ScalarField, AnchoredProofError> {
+    let mut transcript = Transcript::new(TREE_ANCHOR_POSEIDON_DOMAIN);
+    for point in [generator_g, generator_b, anchor, point, r_commitment_1, r_commitment_2] {
+        transcript.absorb_point(point)?;
+    }
+    transcript.challenge::<C>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::G1Affine;
+    use rand::rngs::OsRng;
+    use crate::setup::{anchor_setup, generator_setup, secret_setup};
+
+    #[test]
+    fn test_tree_anchor_verifies() {
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+
+        let tree_anchor: TreeAnchor = tree_anchor_setup(&secret, &g, &b, &mut OsRng).unwrap();
+        assert_eq!(tree_anchor.point, (g * *secret).into_affine());
+        assert_eq!(verify_tree_anchor(&tree_anchor, &g, &b, &anchor), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_tree_anchor_of_other_secret() {
+        let (g, _, b) = generator_setup();
+        let secret = secret_setup();
+        let anchor = anchor_setup(&secret, &b);
+        let other: TreeAnchor = tree_anchor_setup(&secret_setup(), &g, &b, &mut OsRng).unwrap();
+        assert_eq!(verify_tree_anchor(&other, &g, &b, &anchor), Err(AnchoredProofError::InvalidTreeAnchor));
+
+        // The right proof paired with a different A_G
+        let mut swapped: TreeAnchor = tree_anchor_setup(&secret, &g, &b, &mut OsRng).unwrap();
+        swapped.point = other.point;
+        assert_eq!(verify_tree_anchor(&swapped, &g, &b, &anchor), Err(AnchoredProofError::InvalidTreeAnchor));
+
+        let mut identity: TreeAnchor = tree_anchor_setup(&secret, &g, &b, &mut OsRng).unwrap();
+        identity.point = G1Affine::zero();
+        assert_eq!(verify_tree_anchor(&identity, &g, &b, &anchor), Err(AnchoredProofError::InvalidTreeAnchor));
+    }
+}
@@ -1,45 +1,51 @@
-use ark_bn254::{Fr, G1Affine};
+//! Verification of anchored proofs
+//!
+//! The verifier recomputes the leaf from the context's anchor and the proof's
+//! P before checking the Merkle path, so a public leaf and path cannot be
+//! paired with an unrelated P. `AnchoredProof::leaf_hash` is optional; when
+//! present it must match the recomputed leaf.
+
+use ark_bn254::G1Projective;
 use ark_ec::{AffineRepr, CurveGroup};
-use light_poseidon::{Poseidon, PoseidonHasher};
 
-use crate::{AnchoredProof, split_fq_to_fr};
+use crate::issuer::key_id;
+use crate::transcript::Statement;
+use crate::{
+    AnchoredProof, AnchoredProofError, DLEQ_POSEIDON_DOMAIN, LeafEncoding, PoseidonCurve, SCHNORR_POSEIDON_DOMAIN,
+    anchored_leaf_hash_with_encoding, is_canonical_hash,
+};
 
 /// Verification context containing public parameters
-pub struct VerificationContext {
-    pub generator_g: G1Affine,
-    pub generator_h: G1Affine,
-    pub generator_b: G1Affine,
-    pub anchor: G1Affine,
+pub struct VerificationContext<C: CurveGroup = G1Projective> {
+    pub generator_g: C::Affine,
+    pub generator_h: C::Affine,
+    pub generator_b: C::Affine,
+    pub anchor: C::Affine,
     pub tree_root: [u8; 32],
+    pub leaves_len: usize,
+    /// The caller context the prover bound into the challenges
+    pub context: Vec<u8>,
+    /// Encoding the tree's leaves were built with; `LegacyXOnly` only for old roots
+    pub leaf_encoding: LeafEncoding,
 }
 
 /// Verify the DLEQ proof: proves that log_B(U) = log_C(C')
 /// In other words, verifies that U = B*s and C' = C*s for the same s
-fn verify_dleq_proof(
-    public1: &G1Affine,  // U (anchor)
-    public2: &G1Affine,  // C' (modified_commitment)
-    generator1: &G1Affine,  // B
-    generator2: &G1Affine,  // C (commitment)
-    r_commitment_1: &G1Affine,  // R1
-    r_commitment_2: &G1Affine,  // R2
-    response: &Fr,  // z
-) -> bool {
-    // Recompute challenge: H(U, C', R1, R2)
-    let u_limbs = split_fq_to_fr(&public1.x().unwrap());
-    let c_modified_limbs = split_fq_to_fr(&public2.x().unwrap());
-    let r1_limbs = split_fq_to_fr(&r_commitment_1.x().unwrap());
-    let r2_limbs = split_fq_to_fr(&r_commitment_2.x().unwrap());
-
-    let mut poseidon = Poseidon::<Fr>::new_circom(8).unwrap();
-    let challenge = match poseidon.hash(&[
-        u_limbs[0], u_limbs[1],
-        c_modified_limbs[0], c_modified_limbs[1],
-        r1_limbs[0], r1_limbs[1],
-        r2_limbs[0], r2_limbs[1],
-    ]) {
-        Ok(c) => c,
-        Err(_) => return false,
-    };
+fn verify_dleq_proof<C: PoseidonCurve>(
+    statement: &Statement<C>,
+    r_commitment_1: &C::Affine,  // R1
+    r_commitment_2: &C::Affine,  // R2
+    response: &C::ScalarField,  // z
+) -> Result<(), AnchoredProofError> {
+    let (generator1, generator2) = (statement.generator_b, statement.commitment);
+    let (public1, public2) = (statement.anchor, statement.modified_commitment);
+
+    // Recompute challenge: H(statement, R1, R2)
+    let challenge = statement
+        .transcript(DLEQ_POSEIDON_DOMAIN)?
+        .absorb_point(r_commitment_1)?
+        .absorb_point(r_commitment_2)?
+        .challenge::<C>()?;
 
     // Verify: R1 = G1*z - U*challenge
     // Which means: G1*z = R1 + U*challenge
@@ -53,35 +59,39 @@ fn verify_dleq_proof(
     let right2 = *r_commitment_2 + (*public2) * challenge;
     let check2 = left2.into_affine() == right2.into_affine();
 
-    check1 && check2
+    if check1 && check2 {
+        Ok(())
+    } else {
+        Err(AnchoredProofError::DleqFailed)
+    }
 }
 
 /// Verify the Schnorr proof: proves knowledge of t such that R_H = H*t
-fn verify_schnorr_proof(
-    public: &G1Affine,  // R_H
-    generator: &G1Affine,  // H
-    commitment: &G1Affine,  // r (commitment in proof)
-    response: &Fr,  // z
-) -> bool {
-    // Recompute challenge: H(R_H, r)
-    let pk_limbs = split_fq_to_fr(&public.x().unwrap());
-    let r_limbs = split_fq_to_fr(&commitment.x().unwrap());
-
-    let mut poseidon = Poseidon::<Fr>::new_circom(4).unwrap();
-    let challenge = match poseidon.hash(&[
-        pk_limbs[0], pk_limbs[1],
-        r_limbs[0], r_limbs[1],
-    ]) {
-        Ok(c) => c,
-        Err(_) => return false,
-    };
+fn verify_schnorr_proof<C: PoseidonCurve>(
+    statement: &Statement<C>,
+    public: &C::Affine,  // R_H
+    commitment: &C::Affine,  // r (commitment in proof)
+    response: &C::ScalarField,  // z
+) -> Result<(), AnchoredProofError> {
+    let generator = statement.generator_h;
+
+    // Recompute challenge: H(statement, R_H, r)
+    let challenge = statement
+        .transcript(SCHNORR_POSEIDON_DOMAIN)?
+        .absorb_point(public)?
+        .absorb_point(commitment)?
+        .challenge::<C>()?;
 
     // Verify: r = H*z - R_H*challenge
     // Which means: H*z = r + R_H*challenge
     let left = (*generator) * response;
     let right = *commitment + (*public) * challenge;
 
-    left.into_affine() == right.into_affine()
+    if left.into_affine() == right.into_affine() {
+        Ok(())
+    } else {
+        Err(AnchoredProofError::SchnorrFailed)
+    }
 }
 
 /// Verify an anchored merkle proof
@@ -90,136 +100,387 @@ fn verify_schnorr_proof(
 /// 1. The merkle proof (that the leaf is included in the tree)
 /// 2. The DLEQ proof (that anchor and modified_commitment share the same secret)
 /// 3. The Schnorr proof (that R_H is in the group generated by H)
-/// 4. The leaf hash consistency
-pub fn verify_anchored_proof(
-    proof: &AnchoredProof,
-    context: &VerificationContext,
-) -> bool {
+/// 4. The leaf hash consistency: the leaf is recomputed from the anchor and
+///    P, so a valid path for another leaf cannot be paired with this P
+///
+/// Returns the first check that failed, starting with the key id.
+pub fn verify_anchored_proof<C: PoseidonCurve>(
+    proof: &AnchoredProof<C>,
+    context: &VerificationContext<C>,
+) -> Result<(), AnchoredProofError> {
+    check_key_id(proof, context)?;
+
     // Step 1: Verify Merkle Proof
-    // The proof's merkle_proof should verify that leaf_hash is in the tree
+    // The leaf is derived from the context's anchor and the proof's P; a leaf
+    // hash carried by the proof is only compared against it
+    if proof.leaf_index >= context.leaves_len {
+        return Err(AnchoredProofError::MerkleMismatch);
+    }
+    check_subgroup(&proof.p_point)?;
+    let leaf_hash = anchored_leaf_hash_with_encoding(&context.anchor, &proof.p_point, context.leaf_encoding)?;
+    if proof.leaf_hash.is_some_and(|claimed| claimed != leaf_hash) {
+        return Err(AnchoredProofError::MerkleMismatch);
+    }
+    // Trees are padded to a power of two, so the path has exactly one sibling
+    // per level; rs_merkle would ignore any extra hashes
+    let path = proof.merkle_proof.proof_hashes();
+    if !context.leaves_len.is_power_of_two() || path.len() != context.leaves_len.trailing_zeros() as usize {
+        return Err(AnchoredProofError::MerkleMismatch);
+    }
+    // Siblings are hashed with Poseidon, which rejects values outside the field
+    if !path.iter().all(is_canonical_hash) {
+        return Err(AnchoredProofError::MerkleMismatch);
+    }
+
     let merkle_valid = proof.merkle_proof.verify(
         context.tree_root,
-        &proof.merkle_proof.proof_hashes().iter().map(|_| 0).collect::<Vec<_>>(),
-        &[proof.leaf_hash],
-        1 << proof.merkle_proof.proof_hashes().len(), // Approximate leaves count
+        &[proof.leaf_index],
+        &[leaf_hash],
+        context.leaves_len,
     );
 
     if !merkle_valid {
-        return false;
+        return Err(AnchoredProofError::MerkleMismatch);
     }
 
-    // Step 2: Verify DLEQ Proof
-    // Proves that log_B(anchor) = log_C(modified_commitment)
-    let dleq_valid = verify_dleq_proof(
-        &context.anchor,
-        &proof.modified_commitment,
-        &context.generator_b,
+    // Steps 2 and 3: DLEQ and Schnorr proofs
+    verify_anchored_proof_without_merkle(proof, context)
+}
+
+pub fn verify_anchored_proof_without_merkle<C: PoseidonCurve>(
+    proof: &AnchoredProof<C>,
+    context: &VerificationContext<C>,
+) -> Result<(), AnchoredProofError> {
+    check_key_id(proof, context)?;
+
+    // Curves with a cofactor (BLS12-381 G1, Baby Jubjub) must not accept
+    // points with a small-order component from the prover
+    for point in [
         &proof.commitment,
+        &proof.modified_commitment,
+        &proof.p_point,
         &proof.dleq_proof.r_commitment_1,
         &proof.dleq_proof.r_commitment_2,
-        &proof.dleq_proof.response,
-    );
-
-    if !dleq_valid {
-        return false;
-    }
-
-    // Step 3: Verify Schnorr Proof
-    // Reconstruct R_H = C' - P (where P is the point used in the leaf hash)
-    let r_h = (proof.modified_commitment - proof.p_point).into_affine();
-
-    let schnorr_valid = verify_schnorr_proof(
-        &r_h,
-        &context.generator_h,
         &proof.schnorr_proof.commitment,
-        &proof.schnorr_proof.response,
-    );
-
-    if !schnorr_valid {
-        return false;
+    ] {
+        check_subgroup(point)?;
     }
 
-    // Step 4: All checks passed
-    true
-}
+    let statement = Statement::<C> {
+        generator_g: &context.generator_g,
+        generator_h: &context.generator_h,
+        generator_b: &context.generator_b,
+        anchor: &context.anchor,
+        commitment: &proof.commitment,
+        modified_commitment: &proof.modified_commitment,
+        p_point: &proof.p_point,
+        tree_root: &context.tree_root,
+        leaves_len: context.leaves_len,
+        context: &context.context,
+    };
 
-pub fn verify_anchored_proof_without_merkle(
-    proof: &AnchoredProof,
-    context: &VerificationContext,
-) -> bool {
     // Verify DLEQ Proof
-    let dleq_valid = verify_dleq_proof(
-        &context.anchor,
-        &proof.modified_commitment,
-        &context.generator_b,
-        &proof.commitment,
+    // Proves that log_B(anchor) = log_C(modified_commitment)
+    verify_dleq_proof::<C>(
+        &statement,
         &proof.dleq_proof.r_commitment_1,
         &proof.dleq_proof.r_commitment_2,
         &proof.dleq_proof.response,
-    );
-
-    if !dleq_valid {
-        return false;
-    }
+    )?;
 
     // Verify Schnorr Proof
+    // Reconstruct R_H = C' - P (where P is the point used in the leaf hash)
     let r_h = (proof.modified_commitment - proof.p_point).into_affine();
 
-    let schnorr_valid = verify_schnorr_proof(
+    verify_schnorr_proof::<C>(
+        &statement,
         &r_h,
-        &context.generator_h,
         &proof.schnorr_proof.commitment,
         &proof.schnorr_proof.response,
-    );
+    )
+}
+
+/// The proof must name the context's anchor as its issuer key
+fn check_key_id<C: PoseidonCurve>(proof: &AnchoredProof<C>, context: &VerificationContext<C>) -> Result<(), AnchoredProofError> {
+    if proof.key_id != key_id(&context.anchor) {
+        return Err(AnchoredProofError::KeyIdMismatch);
+    }
+    Ok(())
+}
 
-    schnorr_valid
+/// Arkworks' `Valid` check: on the curve and in the prime-order subgroup
+fn check_subgroup<A: AffineRepr>(point: &A) -> Result<(), AnchoredProofError> {
+    point
+        .check()
+        .map_err(|_| AnchoredProofError::InvalidEncoding("point outside the prime-order subgroup".to_string()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{prove::generate_anchored_proof, setup::*, ProofInput};
-    use ark_bn254::Fr;
+    use crate::{prove::generate_anchored_proof, setup::*, ProofInput, PublicParameters};
+    use crate::hash_to_curve::GENERATOR_DST;
+    use crate::test_utils::Fixture;
+    use ark_bn254::{Fr, G1Affine};
+    use rs_merkle::MerkleProof;
+    use zeroize::Zeroizing;
 
     #[test]
     fn test_verify_proof() {
-        // Setup
-        let (g, h, b) = generator_setup();
-        let secret = secret_setup();
-        let blinding = secret_setup();
-        let anchor = anchor_setup(&secret, &b);
-        let range = 8;
-        let tree = tree_setup(range, &anchor, &secret);
-
-        // Select a witness
-        let witness = Fr::from(1u64);
-
-        // Generate proof
-        let input = ProofInput {
-            secret: &secret,
-            witness: &witness,
-            blinding: &blinding,
-            generator_g: &g,
-            generator_h: &h,
-            generator_b: &b,
-            anchor: &anchor,
-            tree: &tree,
-        };
-
-        let proof = generate_anchored_proof(input);
-
-        // Setup verification context
-        let context = VerificationContext {
-            generator_g: g,
-            generator_h: h,
-            generator_b: b,
-            anchor,
-            tree_root: tree.root().unwrap(),
-        };
+        let fixture = Fixture::new(8);
+
+        // Select a witness and generate proof
+        let proof = fixture.prove(1);
 
         // Verify the proof
-        let valid = verify_anchored_proof_without_merkle(&proof, &context);
-        assert!(valid, "Proof verification failed");
+        let valid = verify_anchored_proof_without_merkle(&proof, &fixture.context());
+        assert!(valid.is_ok(), "Proof verification failed");
         println!("Proof verification passed!");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_verify_every_leaf() {
+        let fixture = Fixture::new(8);
+        let context = fixture.context();
+
+        for witness in 1..=(1u64 << 8) {
+            let proof = fixture.prove(witness);
+            assert_eq!(proof.leaf_index, (witness - 1) as usize);
+            assert_eq!(
+                verify_anchored_proof(&proof, &context),
+                Ok(()),
+                "Full verification failed for witness {witness}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_tampered_path_hash() {
+        let fixture = Fixture::new(8);
+        let context = fixture.context();
+        let mut proof = fixture.prove(77);
+
+        let mut hashes = proof.merkle_proof.proof_hashes().to_vec();
+        hashes[3][31] ^= 1;
+        proof.merkle_proof = MerkleProof::new(hashes);
+
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_rejects_truncated_path() {
+        let fixture = Fixture::new(8);
+        let context = fixture.context();
+        let mut proof = fixture.prove(77);
+
+        let mut hashes = proof.merkle_proof.proof_hashes().to_vec();
+        hashes.pop();
+        proof.merkle_proof = MerkleProof::new(hashes);
+
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_rejects_padded_path() {
+        let fixture = Fixture::new(8);
+        let context = fixture.context();
+        let mut proof = fixture.prove(77);
+
+        let mut hashes = proof.merkle_proof.proof_hashes().to_vec();
+        hashes.push(hashes[0]);
+        proof.merkle_proof = MerkleProof::new(hashes);
+
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_rejects_out_of_field_sibling() {
+        let fixture = Fixture::new(8);
+        let context = fixture.context();
+        let mut proof = fixture.prove(77);
+
+        let mut hashes = proof.merkle_proof.proof_hashes().to_vec();
+        hashes[1] = [0xff; 32];
+        proof.merkle_proof = MerkleProof::new(hashes);
+
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_rejects_wrong_leaf_index() {
+        let fixture = Fixture::new(8);
+        let context = fixture.context();
+        let mut proof = fixture.prove(77);
+
+        proof.leaf_index += 1;
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+
+        proof.leaf_index = context.leaves_len;
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    // Tree leaves are public, so a holder of a credential for 30 could take the
+    // leaf and path of 17 and keep its own P and sub-proofs, which only involve
+    // P, not the leaf
+    #[test]
+    fn test_rejects_leaf_unrelated_to_p() {
+        let fixture = Fixture::new(5);
+        let context = fixture.context();
+        let other_index = 16;
+        let other_leaf = fixture.tree.leaves().unwrap()[other_index];
+
+        let mut forged = fixture.prove(30);
+        forged.leaf_hash = Some(other_leaf);
+        forged.leaf_index = other_index;
+        forged.merkle_proof = fixture.tree.proof(other_index).unwrap();
+        assert_eq!(verify_anchored_proof(&forged, &context), Err(AnchoredProofError::MerkleMismatch));
+
+        forged.leaf_hash = None;
+        assert_eq!(verify_anchored_proof(&forged, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_leaf_hash_is_optional() {
+        let fixture = Fixture::new(4);
+        let context = fixture.context();
+        let mut proof = fixture.prove(9);
+
+        proof.leaf_hash = None;
+        assert_eq!(verify_anchored_proof(&proof, &context), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_path_from_other_leaf() {
+        let fixture = Fixture::new(8);
+        let context = fixture.context();
+        let mut proof = fixture.prove(77);
+
+        proof.merkle_proof = fixture.tree.proof(12).unwrap();
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::MerkleMismatch));
+    }
+
+    #[test]
+    fn test_rejects_other_issuer_key() {
+        let fixture = Fixture::new(4);
+        let other = Fixture::new(4);
+        let proof = fixture.prove(3);
+
+        assert_eq!(proof.key_id, key_id(&fixture.anchor));
+        assert_eq!(verify_anchored_proof(&proof, &other.context()), Err(AnchoredProofError::KeyIdMismatch));
+
+        let mut relabelled = fixture.prove(3);
+        relabelled.key_id = key_id(&other.anchor);
+        assert_eq!(verify_anchored_proof(&relabelled, &fixture.context()), Err(AnchoredProofError::KeyIdMismatch));
+    }
+
+    #[test]
+    fn test_legacy_root_needs_migration_flag() {
+        let mut fixture = Fixture::new(4);
+        let range = ValueRange::power_of_two(4).unwrap();
+        fixture.tree =
+            tree_setup_range_with_encoding(range, &fixture.g, &fixture.anchor, &fixture.secret, LeafEncoding::LegacyXOnly).unwrap();
+        // The prover only looks for legacy leaves when told to
+        assert_eq!(fixture.try_prove(5).err(), Some(AnchoredProofError::WitnessOutOfRange));
+        let witness = Zeroizing::new(Fr::from(5u64));
+        let input = ProofInput { leaf_encoding: LeafEncoding::LegacyXOnly, ..fixture.input(&witness) };
+        let proof = generate_anchored_proof(input).unwrap();
+
+        assert_eq!(verify_anchored_proof(&proof, &fixture.context()), Err(AnchoredProofError::MerkleMismatch));
+        let legacy = VerificationContext { leaf_encoding: LeafEncoding::LegacyXOnly, ..fixture.context() };
+        assert!(verify_anchored_proof(&proof, &legacy).is_ok());
+    }
+
+    #[test]
+    fn test_challenges_bind_context_and_generators() {
+        let fixture = Fixture::new(4);
+        let witness = Zeroizing::new(Fr::from(3u64));
+        let input = ProofInput { context: b"session-1", ..fixture.input(&witness) };
+        let proof: AnchoredProof = generate_anchored_proof(input).unwrap();
+
+        let context = VerificationContext { context: b"session-1".to_vec(), ..fixture.context() };
+        assert_eq!(verify_anchored_proof(&proof, &context), Ok(()));
+
+        let replayed = VerificationContext { context: b"session-2".to_vec(), ..fixture.context() };
+        assert_eq!(verify_anchored_proof(&proof, &replayed), Err(AnchoredProofError::DleqFailed));
+
+        // G does not appear in either verification equation, only in the transcript
+        let other_g = VerificationContext { generator_g: fixture.h, ..context };
+        assert_eq!(verify_anchored_proof(&proof, &other_g), Err(AnchoredProofError::DleqFailed));
+    }
+
+    #[test]
+    fn test_rejects_tampered_dleq_response() {
+        let fixture = Fixture::new(4);
+        let context = fixture.context();
+        let mut proof = fixture.prove(3);
+
+        proof.dleq_proof.response += Fr::from(1u64);
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::DleqFailed));
+    }
+
+    #[test]
+    fn test_rejects_tampered_schnorr_response() {
+        let fixture = Fixture::new(4);
+        let context = fixture.context();
+        let mut proof = fixture.prove(3);
+
+        proof.schnorr_proof.response += Fr::from(1u64);
+        assert_eq!(verify_anchored_proof(&proof, &context), Err(AnchoredProofError::SchnorrFailed));
+    }
+
+    #[test]
+    fn test_tree_uses_configured_generator() {
+        let params = PublicParameters::derive(GENERATOR_DST, Some("G"), "H", "B");
+        assert_ne!(params.generator_g.point, G1Affine::generator());
+
+        let fixture = Fixture::with_params(&params, 4);
+        for witness in [1, 9, 16] {
+            assert_eq!(verify_anchored_proof(&fixture.prove(witness), &fixture.context()), Ok(()));
+        }
+
+        // A tree over the canonical generator holds none of this G's points
+        let range = ValueRange::power_of_two(4).unwrap();
+        let canonical = tree_setup_range(range, &G1Affine::generator(), &fixture.anchor, &fixture.secret).unwrap();
+        let fixture = Fixture { tree: canonical, ..fixture };
+        assert_eq!(fixture.try_prove(9).err(), Some(AnchoredProofError::WitnessOutOfRange));
+    }
+
+    #[test]
+    fn test_verify_arbitrary_range() {
+        let fixture = Fixture::new(0);
+        let range = ValueRange::new(18, 120).unwrap();
+        let tree = tree_setup_range(range, &fixture.g, &fixture.anchor, &fixture.secret).unwrap();
+        let fixture = Fixture { tree, ..fixture };
+        let context = fixture.context();
+        assert_eq!(context.leaves_len, 128);
+
+        for age in [18, 19, 64, 119, 120] {
+            let proof = fixture.prove(age);
+            assert_eq!(range.value_at(proof.leaf_index), Some(age));
+            assert_eq!(verify_anchored_proof(&proof, &context), Ok(()));
+        }
+
+        for age in [1, 17, 121, 128] {
+            assert!(matches!(fixture.try_prove(age), Err(AnchoredProofError::WitnessOutOfRange)), "age {age}");
+        }
+    }
+
+    #[test]
+    fn test_verify_set_membership() {
+        let fixture = Fixture::new(0);
+        // Numeric ISO 3166 country codes
+        let allowed = [40u64, 276, 380, 756, 840];
+        let set = tree_setup_set(allowed.map(Fr::from), &fixture.g, &fixture.anchor, &fixture.secret).unwrap();
+        let fixture = Fixture { tree: set.tree, ..fixture };
+        let context = fixture.context();
+        assert_eq!(context.leaves_len, 8);
+
+        for code in allowed {
+            let proof = fixture.prove(code);
+            assert_eq!(set.indices.get(&Fr::from(code)), Some(&proof.leaf_index));
+            assert_eq!(verify_anchored_proof(&proof, &context), Ok(()));
+        }
+
+        assert!(matches!(fixture.try_prove(250), Err(AnchoredProofError::WitnessOutOfRange)));
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("anchored_merkle_proof_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_main"))
+        .current_dir(dir)
+        .env("ANCHORED_ISSUER_PASSPHRASE", "cli test passphrase")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn setup(dir: &Path, extra: &[&str]) -> Output {
+    run(dir, &[&["setup"], extra].concat())
+}
+
+/// Runs a command that must succeed, showing its stderr otherwise
+fn run_ok(dir: &Path, args: &[&str]) -> String {
+    let output = run(dir, args);
+    assert!(output.status.success(), "{args:?} failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Runs a command that must fail, returning its stderr
+fn run_err(dir: &Path, args: &[&str]) -> String {
+    let output = run(dir, args);
+    assert!(!output.status.success(), "{args:?} unexpectedly succeeded");
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn test_setup_refuses_to_overwrite_without_force() {
+    let dir = scratch_dir("setup_twice");
+
+    assert!(setup(&dir, &[]).status.success());
+    let issuer = std::fs::read_to_string(dir.join("issuer.json")).unwrap();
+
+    let second = setup(&dir, &[]);
+    assert!(!second.status.success());
+    assert!(String::from_utf8_lossy(&second.stderr).contains("already exists"));
+    assert_eq!(std::fs::read_to_string(dir.join("issuer.json")).unwrap(), issuer);
+
+    assert!(setup(&dir, &["--force"]).status.success());
+    assert_ne!(std::fs::read_to_string(dir.join("issuer.json")).unwrap(), issuer);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_setup_build_prove_verify_pipeline() {
+    let dir = scratch_dir("pipeline");
+
+    run_ok(&dir, &["setup"]);
+    run_ok(&dir, &["build-tree", "--range", "3"]);
+    run_ok(&dir, &["prove", "--witness", "5", "--context", "session-1"]);
+    assert!(run_ok(&dir, &["verify", "--context", "session-1"]).contains("proof valid"));
+    assert!(run_ok(&dir, &["audit"]).contains("tree matches"));
+    assert!(run_ok(&dir, &["inspect"]).contains("leaf index:             4"));
+
+    // A proof bound to another context is rejected
+    assert!(run_err(&dir, &["verify", "--context", "session-2"]).contains("proof rejected"));
+
+    // A tampered response no longer satisfies the Schnorr proof
+    let proof = std::fs::read_to_string(dir.join("proof.json")).unwrap();
+    let mut tampered: serde_json::Value = serde_json::from_str(&proof).unwrap();
+    tampered["schnorr_proof"]["response"] = "1".into();
+    std::fs::write(dir.join("tampered.json"), tampered.to_string()).unwrap();
+    let reason = run_err(&dir, &["verify", "--context", "session-1", "--proof", "tampered.json"]);
+    assert!(reason.contains("proof rejected: Schnorr"), "{reason}");
+
+    // A tree over a different range has a different root
+    run_ok(&dir, &["build-tree", "--range", "4", "--out", "other_tree.json"]);
+    let reason = run_err(&dir, &["verify", "--context", "session-1", "--tree", "other_tree.json"]);
+    assert!(reason.contains("proof rejected: merkle"), "{reason}");
+    assert!(run_err(&dir, &["audit", "--range", "4"]).contains("does not match"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}